@@ -4,6 +4,7 @@ use crate::maybe::{
     MaybeMutex, MaybeRwLock, MaybeRwLockReadGuard, MaybeRwLockWriteGuard,
     MaybeSend, MaybeSendStatic, MaybeSendSync, Shared,
 };
+use crate::subscription::Subscriptions;
 use core::fmt::{Debug};
 use core::marker::PhantomData;
 use futures::channel::mpsc;
@@ -36,12 +37,66 @@ pub trait Model: MaybeSendSync + 'static {
 
     fn update(&mut self, message: Self::Message, ctx: &mut UpdateContext<Self::ForApp>);
 
+    /// Called once when the host starts, before it processes any message, so the model can emit
+    /// the commands that load its initial data. The host only calls it on the root model, which
+    /// is responsible for initializing its children.
+    fn init(&mut self, _ctx: &mut UpdateContext<Self::ForApp>) {}
+
+    /// The subscriptions that should be running in the current state. The host calls this after
+    /// every update, starting the subscriptions that appeared and stopping the ones that are gone.
+    fn subscriptions(&self) -> Subscriptions<Self>
+    where
+        Self: Sized,
+    {
+        Subscriptions::none()
+    }
+
     #[doc(hidden)]
     fn __accumulate_signals(
         &self,
         signals: &mut VecDeque<Shared<dyn FlushSignals>>,
         _token: __private::Token,
     );
+
+    // messages aren't required to implement `Debug`, so panics are reported with a short
+    // description of the message instead
+    #[doc(hidden)]
+    fn __describe_message(_message: &Self::Message, _token: __private::Token) -> &'static str
+    where
+        Self: Sized,
+    {
+        core::any::type_name::<Self::Message>()
+    }
+
+    // identifies the variants marked with `#[emyu(coalesce)]`, of which the host keeps at most one
+    // pending message
+    #[doc(hidden)]
+    fn __coalesce_key(_message: &Self::Message, _token: __private::Token) -> Option<usize>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    // wraps a lifecycle transition into the message of the `#[emyu(on_lifecycle)]` updater
+    #[doc(hidden)]
+    fn __lifecycle_message(
+        _lifecycle: crate::Lifecycle,
+        _token: __private::Token,
+    ) -> Option<Self::Message>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    #[cfg(feature = "persistence")]
+    #[doc(hidden)]
+    fn __load_fields(&mut self, _store: &dyn crate::KeyValueStore, _token: __private::Token) {}
+
+    #[cfg(feature = "persistence")]
+    #[doc(hidden)]
+    fn __save_fields(&self, _store: &dyn crate::KeyValueStore, _token: __private::Token) {}
 }
 
 pub trait ModelGetterHandler<M: ModelGetterMessage>: Model {
@@ -53,8 +108,20 @@ pub enum Error {
     #[error("the channel to the host is closed")]
     HostChannelClosed,
 
+    #[error("the channel to the host is full")]
+    HostChannelFull,
+
+    #[error("the host dropped the message before replying")]
+    ReplyDropped,
+
+    #[error("the host dropped the message before applying it")]
+    MessageDropped,
+
     #[error("the channel to the model getter is closed")]
     ModelGetterChannelClosed,
+
+    #[error("the host has no model factory to reset the root model with")]
+    NoModelFactory,
 }
 
 impl From<HostChannelClosed> for Error {
@@ -220,7 +287,9 @@ impl<T> Signal<T> {
         let mut subscribers = self.0.subscribers.lock();
         subscribers.push(status_tx);
         SignalSubscriber {
-            reader: self.reader(),
+            // holding the data instead of the signal itself lets the signal be destroyed while
+            // it is still being subscribed to
+            data: Shared::clone(&self.0.data),
             status_rx,
         }
     }
@@ -233,6 +302,11 @@ impl<T> Signal<T> {
         SignalWriter(self.clone())
     }
 
+    #[cfg(feature = "persistence")]
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.0.dirty.load(Ordering::Acquire)
+    }
+
     #[doc(hidden)]
     pub fn __to_dyn_flush_signals(&self, _: __private::Token) -> Shared<dyn FlushSignals>
     where
@@ -254,23 +328,24 @@ impl<T: Default> Default for Signal<T> {
     }
 }
 
-impl<T> Drop for Signal<T> {
-    fn drop(&mut self) {
-        for subscriber in &mut *self.0.subscribers.lock() {
-            subscriber.try_send(SignalStatus::Destroyed).ok();
-        }
-    }
-}
-
 struct SignalRepr<T> {
     data: Shared<MaybeRwLock<T>>,
     subscribers: MaybeMutex<Vec<mpsc::Sender<SignalStatus>>>,
     dirty: AtomicBool,
 }
 
+impl<T> Drop for SignalRepr<T> {
+    fn drop(&mut self) {
+        for subscriber in &mut *self.subscribers.lock() {
+            subscriber.try_send(SignalStatus::Destroyed).ok();
+        }
+    }
+}
+
 #[doc(hidden)]
 pub trait FlushSignals: MaybeSendSync {
     fn __flush(&self, _token: __private::Token);
+    fn __destroy(&self, _token: __private::Token);
 }
 
 impl<T: MaybeSendSync> FlushSignals for SignalRepr<T> {
@@ -283,6 +358,12 @@ impl<T: MaybeSendSync> FlushSignals for SignalRepr<T> {
             subscribers.retain(|s| !s.is_closed());
         }
     }
+
+    fn __destroy(&self, _: __private::Token) {
+        for mut subscriber in self.subscribers.lock().drain(..) {
+            subscriber.try_send(SignalStatus::Destroyed).ok();
+        }
+    }
 }
 
 impl<T: MaybeSendSync> FlushSignals for Vec<Signal<T>> {
@@ -291,16 +372,22 @@ impl<T: MaybeSendSync> FlushSignals for Vec<Signal<T>> {
             signal.0.__flush(crate::__token());
         }
     }
+
+    fn __destroy(&self, _: __private::Token) {
+        for signal in self {
+            signal.0.__destroy(crate::__token());
+        }
+    }
 }
 
 pub struct SignalSubscriber<T> {
-    reader: SignalReader<T>,
+    data: Shared<MaybeRwLock<T>>,
     status_rx: mpsc::Receiver<SignalStatus>,
 }
 
 impl<T> SignalSubscriber<T> {
     pub fn read(&self) -> MaybeRwLockReadGuard<'_, T> {
-        self.reader.read()
+        self.data.read()
     }
 
     pub async fn recv_status(&mut self) -> Option<SignalStatus> {
@@ -0,0 +1,375 @@
+use crate::maybe::{MaybeMutex, Shared};
+use crate::{Error, HostChannelClosed};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::channel::{mpsc, oneshot};
+use futures::stream::FusedStream;
+use futures::{SinkExt, Stream, StreamExt, ready};
+
+/// The lane a message is sent in. The host always takes the next message from the highest
+/// priority lane that has one, while messages within a lane keep the order they were sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// For messages the user is waiting on, like input.
+    High,
+
+    #[default]
+    Normal,
+
+    /// For background work, like sync results.
+    Low,
+}
+
+pub(crate) const LANES: usize = 3;
+
+/// Resolved once the message it was sent with has been applied and the signals flushed.
+pub(crate) type Ack = oneshot::Sender<()>;
+
+pub(crate) struct Envelope<T> {
+    pub(crate) message: T,
+    pub(crate) ack: Option<Ack>,
+}
+
+impl<T> Envelope<T> {
+    pub(crate) fn new(message: T) -> Self {
+        Self { message, ack: None }
+    }
+}
+
+impl Priority {
+    pub(crate) const ALL: [Self; LANES] = [Self::High, Self::Normal, Self::Low];
+
+    pub(crate) fn lane(self) -> usize {
+        self as usize
+    }
+}
+
+/// What happens when a message is sent while its lane of the channel to the host already holds
+/// [`buffer_size`](crate::HostBuilder::buffer_size) messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Senders wait until the host makes room.
+    #[default]
+    Block,
+
+    /// The message being sent is dropped.
+    DropNewest,
+
+    /// The oldest queued message is dropped to make room for the one being sent.
+    DropOldest,
+
+    /// Nothing is dropped and senders never wait, but a warning is logged whenever the queue
+    /// grows past the buffer size.
+    Unbounded,
+}
+
+#[derive(Default)]
+struct State {
+    queued: usize,
+    // the number of messages at the front of the queue the receiver should discard
+    skip: usize,
+    dropped: u64,
+    above_high_water: bool,
+}
+
+enum SenderInner<T> {
+    Bounded(mpsc::Sender<T>),
+    Unbounded(mpsc::UnboundedSender<T>),
+}
+
+enum ReceiverInner<T> {
+    Bounded(mpsc::Receiver<T>),
+    Unbounded(mpsc::UnboundedReceiver<T>),
+}
+
+impl<T> Clone for SenderInner<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Bounded(tx) => Self::Bounded(tx.clone()),
+            Self::Unbounded(tx) => Self::Unbounded(tx.clone()),
+        }
+    }
+}
+
+struct LaneSender<T> {
+    inner: SenderInner<T>,
+    state: Shared<MaybeMutex<State>>,
+    backpressure: Backpressure,
+    capacity: usize,
+}
+
+struct LaneReceiver<T> {
+    inner: ReceiverInner<T>,
+    state: Shared<MaybeMutex<State>>,
+    capacity: usize,
+}
+
+/// Reads how many messages were dropped because of [`Backpressure`], across all lanes.
+#[derive(Clone)]
+pub(crate) struct DroppedMessages([Shared<MaybeMutex<State>>; LANES]);
+
+impl DroppedMessages {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.iter().map(|state| state.lock().dropped).sum()
+    }
+}
+
+fn lane<T>(capacity: usize, backpressure: Backpressure) -> (LaneSender<T>, LaneReceiver<T>) {
+    let state = Shared::new(MaybeMutex::new(State::default()));
+    let (tx, rx) = match backpressure {
+        Backpressure::Block => {
+            let (tx, rx) = mpsc::channel(capacity);
+            (SenderInner::Bounded(tx), ReceiverInner::Bounded(rx))
+        }
+        _ => {
+            let (tx, rx) = mpsc::unbounded();
+            (SenderInner::Unbounded(tx), ReceiverInner::Unbounded(rx))
+        }
+    };
+    let sender = LaneSender {
+        inner: tx,
+        state: Shared::clone(&state),
+        backpressure,
+        capacity,
+    };
+    let receiver = LaneReceiver {
+        inner: rx,
+        state,
+        capacity,
+    };
+    (sender, receiver)
+}
+
+impl<T> LaneSender<T> {
+    async fn send(&mut self, message: T) -> Result<(), HostChannelClosed> {
+        match &mut self.inner {
+            SenderInner::Bounded(tx) => tx.send(message).await.map_err(|_| HostChannelClosed),
+            SenderInner::Unbounded(_) => self.push(message),
+        }
+    }
+
+    fn try_send(&mut self, message: T) -> Result<(), Error> {
+        match &mut self.inner {
+            SenderInner::Bounded(tx) => tx.try_send(message).map_err(|error| {
+                if error.is_full() {
+                    Error::HostChannelFull
+                } else {
+                    Error::HostChannelClosed
+                }
+            }),
+            SenderInner::Unbounded(_) => Ok(self.push(message)?),
+        }
+    }
+
+    // the state stays locked while sending so the receiver never sees a message it wasn't told
+    // about
+    fn push(&self, message: T) -> Result<(), HostChannelClosed> {
+        let SenderInner::Unbounded(tx) = &self.inner else {
+            unreachable!("only unbounded channels apply backpressure on the sending side");
+        };
+        let mut state = self.state.lock();
+        if let Backpressure::DropNewest = self.backpressure
+            && state.queued - state.skip >= self.capacity
+        {
+            state.dropped += 1;
+            tracing::trace!(
+                dropped = state.dropped,
+                "host channel is full, dropping newest message"
+            );
+            return Ok(());
+        }
+        tx.unbounded_send(message).map_err(|_| HostChannelClosed)?;
+        state.queued += 1;
+        match self.backpressure {
+            Backpressure::DropOldest if state.queued - state.skip > self.capacity => {
+                state.skip += 1;
+                state.dropped += 1;
+                tracing::trace!(
+                    dropped = state.dropped,
+                    "host channel is full, dropping oldest message"
+                );
+            }
+            Backpressure::Unbounded if state.queued > self.capacity && !state.above_high_water => {
+                state.above_high_water = true;
+                tracing::warn!(
+                    queued = state.queued,
+                    high_water = self.capacity,
+                    "host channel grew past its high-water mark"
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for LaneSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            state: Shared::clone(&self.state),
+            backpressure: self.backpressure,
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<T> LaneReceiver<T> {
+    fn try_recv(&mut self) -> Option<T> {
+        loop {
+            let message = match &mut self.inner {
+                ReceiverInner::Bounded(rx) => return rx.try_recv().ok(),
+                ReceiverInner::Unbounded(rx) => rx.try_recv().ok()?,
+            };
+            if let Some(message) = self.accept(message) {
+                return Some(message);
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        match &mut self.inner {
+            ReceiverInner::Bounded(rx) => rx.close(),
+            ReceiverInner::Unbounded(rx) => rx.close(),
+        }
+    }
+
+    // discards the message if it was pushed out by a newer one
+    fn accept(&self, message: T) -> Option<T> {
+        let mut state = self.state.lock();
+        state.queued -= 1;
+        if state.queued <= self.capacity / 2 {
+            state.above_high_water = false;
+        }
+        if state.skip > 0 {
+            state.skip -= 1;
+            return None;
+        }
+        Some(message)
+    }
+}
+
+impl<T> Stream for LaneReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            let message = match &mut self.inner {
+                ReceiverInner::Bounded(rx) => return rx.poll_next_unpin(cx),
+                ReceiverInner::Unbounded(rx) => ready!(rx.poll_next_unpin(cx)),
+            };
+            match message {
+                Some(message) => {
+                    if let Some(message) = self.accept(message) {
+                        return Poll::Ready(Some(message));
+                    }
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<T> FusedStream for LaneReceiver<T> {
+    fn is_terminated(&self) -> bool {
+        match &self.inner {
+            ReceiverInner::Bounded(rx) => rx.is_terminated(),
+            ReceiverInner::Unbounded(rx) => rx.is_terminated(),
+        }
+    }
+}
+
+pub(crate) struct MessageSender<T> {
+    lanes: [LaneSender<T>; LANES],
+}
+
+pub(crate) struct MessageReceiver<T> {
+    lanes: [LaneReceiver<T>; LANES],
+    // the number of lanes, from the highest priority down, that messages are taken from
+    open: usize,
+}
+
+pub(crate) fn channel<T>(
+    capacities: [usize; LANES],
+    backpressure: Backpressure,
+) -> (MessageSender<T>, MessageReceiver<T>) {
+    let [high, normal, low] = capacities.map(|capacity| lane(capacity, backpressure));
+    (
+        MessageSender {
+            lanes: [high.0, normal.0, low.0],
+        },
+        MessageReceiver {
+            lanes: [high.1, normal.1, low.1],
+            open: LANES,
+        },
+    )
+}
+
+impl<T> MessageSender<T> {
+    pub(crate) async fn send(
+        &mut self,
+        priority: Priority,
+        message: T,
+    ) -> Result<(), HostChannelClosed> {
+        self.lanes[priority.lane()].send(message).await
+    }
+
+    pub(crate) fn try_send(&mut self, priority: Priority, message: T) -> Result<(), Error> {
+        self.lanes[priority.lane()].try_send(message)
+    }
+
+    pub(crate) fn dropped_messages(&self) -> DroppedMessages {
+        DroppedMessages(self.lanes.each_ref().map(|lane| Shared::clone(&lane.state)))
+    }
+}
+
+impl<T> Clone for MessageSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            lanes: self.lanes.each_ref().map(LaneSender::clone),
+        }
+    }
+}
+
+impl<T> MessageReceiver<T> {
+    /// Returns the next queued message of the lane of `priority` without waiting.
+    pub(crate) fn try_recv_from(&mut self, priority: Priority) -> Option<T> {
+        self.lanes[priority.lane()].try_recv()
+    }
+
+    /// Leaves everything but [`Priority::High`] messages in the channel while suspended.
+    pub(crate) fn suspend(&mut self, suspended: bool) {
+        self.open = if suspended { 1 } else { LANES };
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.lanes.iter_mut().for_each(LaneReceiver::close);
+    }
+}
+
+impl<T> Stream for MessageReceiver<T> {
+    type Item = (Priority, T);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let open = self.open;
+        let mut ended = 0;
+        for (priority, lane) in Priority::ALL.into_iter().zip(&mut self.lanes[..open]) {
+            match lane.poll_next_unpin(cx) {
+                Poll::Ready(Some(message)) => return Poll::Ready(Some((priority, message))),
+                Poll::Ready(None) => ended += 1,
+                Poll::Pending => {}
+            }
+        }
+        if ended == open {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> FusedStream for MessageReceiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.lanes.iter().all(FusedStream::is_terminated)
+    }
+}
@@ -1,5 +1,7 @@
 use crate::maybe::{MaybeLocalBoxFuture, MaybeSend, MaybeSendSync};
 use crate::{Application, CommandContext};
+use alloc::borrow::Cow;
+use alloc::string::String;
 use core::fmt;
 use core::fmt::Debug;
 use core::marker::PhantomData;
@@ -26,6 +28,47 @@ maybe_async_trait! {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key(Cow<'static, str>);
+
+impl Key {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&'static str> for Key {
+    fn from(value: &'static str) -> Self {
+        Self(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for Key {
+    fn from(value: String) -> Self {
+        Self(Cow::Owned(value))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// What happens when a keyed command is emitted while another command with the same key is
+/// still in flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandPolicy {
+    /// Cancels the command in flight, along with any queued after it, and runs the new one.
+    Switch,
+
+    /// Drops the new command.
+    Exhaust,
+
+    /// Runs the new command once every command before it with the same key has finished.
+    Queue,
+}
+
 type DynCommandFnRepr<ForApp> =
     Box<dyn_Maybe!(SendSync for<'rt> CommandFnHelper<'rt, ForApp, Fut = MaybeLocalBoxFuture<'rt, ()>>)>;
 
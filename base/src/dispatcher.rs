@@ -1,10 +1,10 @@
+use crate::channel::{Envelope, MessageSender};
 use crate::maybe::Shared;
 use crate::{
-    __private, Application, Model, ModelBase, ModelGetterHandler, ModelGetterMessage,
-    HostChannelClosed, Signal,
+    __private, Application, Error, Model, ModelBase, ModelGetterHandler, ModelGetterMessage,
+    HostChannelClosed, Priority, Signal,
 };
-use futures::SinkExt;
-use futures::channel::mpsc;
+use futures::channel::oneshot;
 use std::convert::identity;
 
 type RootModelOf<M> = <<M as Model>::ForApp as Application>::RootModel;
@@ -13,8 +13,9 @@ type Mapper<M> = dyn_Maybe!(SendSync Fn(<M as Model>::Message) -> RootMessageOf<
 
 
 pub struct Updater<M: Model> {
-    tx: mpsc::Sender<RootMessageOf<M>>,
+    tx: MessageSender<Envelope<RootMessageOf<M>>>,
     mapper: Shared<Mapper<M>>,
+    priority: Priority,
 }
 
 impl<R> Updater<R>
@@ -22,10 +23,11 @@ where
     R: Model,
     <R as Model>::ForApp: Application<RootModel = R>,
 {
-    pub(crate) fn new(tx: mpsc::Sender<RootMessageOf<R>>) -> Self {
+    pub(crate) fn new(tx: MessageSender<Envelope<RootMessageOf<R>>>) -> Self {
         Self {
             tx,
             mapper: Shared::new(identity),
+            priority: Priority::default(),
         }
     }
 }
@@ -36,9 +38,8 @@ impl<M: Model> Updater<M> {
         message: M::Message,
     ) -> Result<(), HostChannelClosed> {
         self.tx
-            .send((self.mapper)(message))
+            .send(self.priority, Envelope::new((self.mapper)(message)))
             .await
-            .map_err(|_| HostChannelClosed)
     }
 
     pub async fn send(&mut self, message: M::Message) {
@@ -47,6 +48,64 @@ impl<M: Model> Updater<M> {
             .expect("the channel to the host is closed")
     }
 
+    /// Sends `message` at `priority` instead of the updater's own priority.
+    pub async fn send_with(&mut self, priority: Priority, message: M::Message) {
+        self.tx
+            .send(priority, Envelope::new((self.mapper)(message)))
+            .await
+            .expect("the channel to the host is closed")
+    }
+
+    /// Sends `message` and waits until the host has applied it and flushed the signals, so that
+    /// getters read afterwards reflect it. Commands emitted for it have run by then if the host
+    /// runs them [sequentially](crate::CommandExecution::Sequential), and have only been spawned
+    /// otherwise.
+    ///
+    /// Fails with [`Error::MessageDropped`] if the host discards the message instead, e.g. because
+    /// of its [`Backpressure`](crate::Backpressure) policy, a middleware dropping it, the update
+    /// panicking or the host stopping first.
+    pub async fn send_and_wait(&mut self, message: M::Message) -> Result<(), Error> {
+        let (ack, applied) = oneshot::channel();
+        let envelope = Envelope {
+            message: (self.mapper)(message),
+            ack: Some(ack),
+        };
+        self.tx.send(self.priority, envelope).await?;
+        applied.await.map_err(|_| Error::MessageDropped)
+    }
+
+    /// Sends `message` without waiting, failing with [`Error::HostChannelFull`] if the channel to
+    /// the host has no room left. With a [`Backpressure`](crate::Backpressure) policy other than
+    /// `Block`, the channel never reports being full.
+    pub fn try_send_now(&mut self, message: M::Message) -> Result<(), Error> {
+        self.tx
+            .try_send(self.priority, Envelope::new((self.mapper)(message)))
+    }
+
+    /// Sends `message`, blocking the current thread until the channel to the host has room.
+    ///
+    /// Meant for synchronous callers such as FFI callbacks. Calling it from the thread the host
+    /// runs on deadlocks once the channel is full.
+    pub fn send_blocking(&mut self, message: M::Message) -> Result<(), Error> {
+        Ok(futures::executor::block_on(self.try_send(message))?)
+    }
+
+    /// The number of messages dropped so far because of the host's
+    /// [`Backpressure`](crate::Backpressure) policy.
+    pub fn dropped_messages(&self) -> u64 {
+        self.tx.dropped_messages().get()
+    }
+
+    /// Returns an updater sending every message at `priority`. Wrapped updaters can be built from
+    /// it to send all of their messages at that priority.
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
     pub fn zoom<Child>(self, lens: fn(<Child as Model>::Message) -> M::Message) -> Updater<Child>
     where
         Child: Model<ForApp = M::ForApp>,
@@ -59,6 +118,7 @@ impl<M: Model> Updater<M> {
         Updater {
             tx: self.tx.clone(),
             mapper: child_mapper,
+            priority: self.priority,
         }
     }
 }
@@ -68,6 +128,7 @@ impl<M: Model> Clone for Updater<M> {
         Self {
             tx: self.tx.clone(),
             mapper: Shared::clone(&self.mapper),
+            priority: self.priority,
         }
     }
 }
@@ -79,13 +140,18 @@ pub trait WrappedUpdater: Clone + __private::Sealed {
     fn __new(updater: Updater<Self::Model>, _token: __private::Token) -> Self;
 }
 
+type Resolver<M> = dyn_Maybe!(SendSync Fn() -> ModelBase<M>);
+
 pub struct Getter<M> {
-    model: ModelBase<M>,
+    // walks down from the root on every read, so zoomed getters follow a reset of the host
+    resolve: Shared<Resolver<M>>,
 }
 
-impl<M> Getter<M> {
+impl<M: Model> Getter<M> {
     pub(crate) fn new(model: ModelBase<M>) -> Self {
-        Self { model }
+        Self {
+            resolve: Shared::new(move || model.clone()),
+        }
     }
 
     pub fn get<Msg>(&self) -> Signal<Msg::Data>
@@ -93,16 +159,16 @@ impl<M> Getter<M> {
         Msg: ModelGetterMessage,
         M: ModelGetterHandler<Msg>,
     {
-        self.model.get()
+        (self.resolve)().get()
     }
 
     pub fn zoom<Child>(self, lens: fn(&M) -> &ModelBase<Child>) -> Getter<Child>
     where
-        M: Model,
         Child: Model<ForApp = M::ForApp>,
     {
+        let parent = self.resolve;
         Getter {
-            model: self.model.zoom(lens),
+            resolve: Shared::new(move || parent().zoom(lens)),
         }
     }
 }
@@ -110,7 +176,7 @@ impl<M> Getter<M> {
 impl<M> Clone for Getter<M> {
     fn clone(&self) -> Self {
         Self {
-            model: self.model.clone(),
+            resolve: Shared::clone(&self.resolve),
         }
     }
 }
@@ -0,0 +1,52 @@
+use crate::maybe::{MaybeMutex, Shared};
+use alloc::string::String;
+use alloc::vec::Vec;
+use futures::channel::mpsc;
+
+/// Something noteworthy that happened inside the host, see [`Host::events`](crate::Host::events).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum HostEvent {
+    /// An update or a command panicked and the panic was caught by the host.
+    Panicked {
+        /// The message being applied, or the [`Debug`] output of the command being run.
+        message_debug: String,
+
+        /// The panic message, if it was a string.
+        payload: String,
+    },
+
+    /// A fallible updater marked with `#[emyu(errors(host))]` returned an error.
+    UpdateFailed {
+        /// The message that failed.
+        message_debug: String,
+
+        /// The [`Display`](core::fmt::Display) output of the error.
+        error: String,
+    },
+}
+
+pub type HostEvents = mpsc::UnboundedReceiver<HostEvent>;
+
+#[derive(Clone)]
+pub(crate) struct EventHub(Shared<MaybeMutex<Vec<mpsc::UnboundedSender<HostEvent>>>>);
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self(Shared::new(MaybeMutex::new(Vec::new())))
+    }
+}
+
+impl EventHub {
+    pub(crate) fn subscribe(&self) -> HostEvents {
+        let (tx, rx) = mpsc::unbounded();
+        self.0.lock().push(tx);
+        rx
+    }
+
+    pub(crate) fn emit(&self, event: HostEvent) {
+        self.0
+            .lock()
+            .retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+    }
+}
@@ -33,12 +33,23 @@ pub use spawner::FrbSpawner;
 #[cfg(feature = "tokio")]
 pub use spawner::TokioSpawner;
 
-use crate::{Application, Host, HostBuilder};
+use crate::channel::DroppedMessages;
+use crate::event::EventHub;
+use crate::{
+    Application, Error, Host, HostBuilder, HostEvents, Lifecycle, LifecycleHandle, ResetHandle,
+    ShutdownHandle, SourceHandle,
+};
 use crate::{WrappedGetter, WrappedUpdater};
 
 pub struct AppHandle<A: Application, WU, WG> {
     updater: WU,
     getter: WG,
+    shutdown: ShutdownHandle,
+    sources: SourceHandle,
+    reset: ResetHandle,
+    lifecycle: LifecycleHandle,
+    events: EventHub,
+    dropped_messages: DroppedMessages,
     _app: PhantomData<A>,
 }
 
@@ -54,10 +65,22 @@ where
         let host = builder_fn(HostBuilder::new());
         let updater = host.updater();
         let getter = host.getter();
+        let shutdown = host.shutdown_handle();
+        let sources = host.source_handle();
+        let reset = host.reset_handle();
+        let lifecycle = host.lifecycle_handle();
+        let events = host.event_hub();
+        let dropped_messages = host.dropped_messages_counter();
         S::spawn_detached(host.run());
         Self {
             updater: WU::__new(updater, crate::__token()),
             getter: WG::__new(getter, crate::__token()),
+            shutdown,
+            sources,
+            reset,
+            lifecycle,
+            events,
+            dropped_messages,
             _app: PhantomData,
         }
     }
@@ -88,4 +111,43 @@ where
     pub fn getter(&self) -> WG {
         self.getter.clone()
     }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    pub fn source_handle(&self) -> SourceHandle {
+        self.sources.clone()
+    }
+
+    pub fn reset_handle(&self) -> ResetHandle {
+        self.reset.clone()
+    }
+
+    pub fn lifecycle_handle(&self) -> LifecycleHandle {
+        self.lifecycle.clone()
+    }
+
+    /// See [`Host::set_lifecycle`].
+    pub fn set_lifecycle(&self, lifecycle: Lifecycle) {
+        self.lifecycle.set(lifecycle)
+    }
+
+    /// See [`Host::events`].
+    pub fn events(&self) -> HostEvents {
+        self.events.subscribe()
+    }
+
+    /// See [`Updater::dropped_messages`](crate::Updater::dropped_messages).
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.get()
+    }
+
+    pub async fn shutdown(&self) {
+        self.shutdown.shutdown().await
+    }
+
+    pub async fn reset(&self) -> Result<(), Error> {
+        self.reset.reset().await
+    }
 }
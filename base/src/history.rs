@@ -0,0 +1,200 @@
+use crate::{Key, ModelBase, Signal};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// State which can be captured and later put back by a [`History`].
+pub trait Snapshot {
+    type Snapshot;
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    /// Puts `snapshot` back. Signals should be written through their writers so subscribers are
+    /// notified of the change.
+    fn restore(&mut self, snapshot: Self::Snapshot);
+}
+
+struct Entry<S> {
+    snapshot: S,
+    group: Option<Key>,
+}
+
+/// An undo/redo stack of snapshots.
+///
+/// A history is usually kept as a field of the model it records. To let the view bind to
+/// [`can_undo`](Self::can_undo) and [`can_redo`](Self::can_redo), store the signals in fields of
+/// the model as well and expose them through getters:
+///
+/// ```ignore
+/// let history = History::new(100);
+/// EditorModel {
+///     can_undo: history.can_undo(),
+///     can_redo: history.can_redo(),
+///     history,
+///     // ...
+/// }
+/// ```
+pub struct History<S> {
+    past: VecDeque<Entry<S>>,
+    future: Vec<S>,
+    depth: usize,
+    grouping: bool,
+    can_undo: Signal<bool>,
+    can_redo: Signal<bool>,
+}
+
+impl<S> History<S> {
+    /// Creates a history which keeps at most `depth` undo steps, forgetting the oldest ones first.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            past: VecDeque::new(),
+            future: Vec::new(),
+            depth,
+            grouping: false,
+            can_undo: Signal::new(false),
+            can_redo: Signal::new(false),
+        }
+    }
+
+    pub fn can_undo(&self) -> Signal<bool> {
+        self.can_undo.clone()
+    }
+
+    pub fn can_redo(&self) -> Signal<bool> {
+        self.can_redo.clone()
+    }
+
+    /// Records `snapshot`, the state from right before a change, as a new undo step. Anything
+    /// that could be redone is forgotten.
+    pub fn record(&mut self, snapshot: S) {
+        self.push(snapshot, None);
+    }
+
+    /// Like [`record`](Self::record), but consecutive calls with the same `group` are coalesced
+    /// into a single undo step, which restores the state from before the first of them. Useful
+    /// for typing, dragging and other changes that arrive as a burst of messages.
+    pub fn record_grouped(&mut self, group: impl Into<Key>, snapshot: S) {
+        let group = group.into();
+        let coalesce = self.grouping
+            && self
+                .past
+                .back()
+                .is_some_and(|entry| entry.group.as_ref() == Some(&group));
+        if coalesce {
+            self.future.clear();
+            self.sync_signals();
+        } else {
+            self.push(snapshot, Some(group));
+        }
+    }
+
+    /// Makes the next [`record_grouped`](Self::record_grouped) call start a new undo step, even
+    /// if its group is the same as the previous one.
+    pub fn close_group(&mut self) {
+        self.grouping = false;
+    }
+
+    /// Steps back, returning the snapshot to restore. `current` is the state right now, which
+    /// becomes the next redo step.
+    pub fn undo(&mut self, current: S) -> Option<S> {
+        let entry = self.past.pop_back()?;
+        self.future.push(current);
+        self.grouping = false;
+        self.sync_signals();
+        Some(entry.snapshot)
+    }
+
+    /// Steps forward again, returning the snapshot to restore. `current` is the state right now,
+    /// which becomes the next undo step.
+    pub fn redo(&mut self, current: S) -> Option<S> {
+        let snapshot = self.future.pop()?;
+        self.past.push_back(Entry {
+            snapshot: current,
+            group: None,
+        });
+        self.grouping = false;
+        self.sync_signals();
+        Some(snapshot)
+    }
+
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+        self.grouping = false;
+        self.sync_signals();
+    }
+
+    fn push(&mut self, snapshot: S, group: Option<Key>) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.past.len() == self.depth {
+            self.past.pop_front();
+        }
+        self.grouping = group.is_some();
+        self.past.push_back(Entry { snapshot, group });
+        self.future.clear();
+        self.sync_signals();
+    }
+
+    // only writes when the value changes, so subscribers aren't woken on every recorded step
+    fn sync_signals(&self) {
+        for (signal, value) in [
+            (&self.can_undo, !self.past.is_empty()),
+            (&self.can_redo, !self.future.is_empty()),
+        ] {
+            if *signal.reader().read() != value {
+                signal.writer().set(value);
+            }
+        }
+    }
+}
+
+/// Shortcuts for recording a child model, kept as a [`ModelBase`] field of the model holding the
+/// history. They don't work on the root model, which the host keeps locked while it's updated;
+/// record it with [`record`](History::record) and [`Snapshot::snapshot`] instead.
+impl<S> History<S> {
+    /// Records the current state of `model`.
+    pub fn record_model<M: Snapshot<Snapshot = S>>(&mut self, model: &ModelBase<M>) {
+        self.record(model.read().snapshot());
+    }
+
+    pub fn record_model_grouped<M: Snapshot<Snapshot = S>>(
+        &mut self,
+        group: impl Into<Key>,
+        model: &ModelBase<M>,
+    ) {
+        self.record_grouped(group, model.read().snapshot());
+    }
+
+    /// Undoes the last step on `model`. Returns whether there was anything to undo.
+    pub fn undo_model<M: Snapshot<Snapshot = S>>(&mut self, model: &ModelBase<M>) -> bool {
+        let mut model = model.write();
+        match self.undo(model.snapshot()) {
+            Some(snapshot) => {
+                model.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the last undone step on `model`. Returns whether there was anything to redo.
+    pub fn redo_model<M: Snapshot<Snapshot = S>>(&mut self, model: &ModelBase<M>) -> bool {
+        let mut model = model.write();
+        match self.redo(model.snapshot()) {
+            Some(snapshot) => {
+                model.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<S> Default for History<S> {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
@@ -1,28 +1,95 @@
-use crate::{Application, Command, Model, ModelGetterHandler, ModelGetterMessage};
-use crate::maybe::{MaybeRwLockReadGuard, MaybeSendSync, Shared};
-use crate::{FlushSignals, Interceptor, ModelBase, ModelBaseReader, Signal};
+use crate::maybe::{
+    MaybeLocalBoxFuture, MaybeLocalBoxStream, MaybeRwLock, MaybeRwLockReadGuard,
+    MaybeRwLockWriteGuard, MaybeSend, MaybeSendSync, Shared,
+};
+use crate::{
+    Application, Command, CommandPolicy, Error, Key, Model, ModelGetterHandler, ModelGetterMessage,
+};
+use crate::channel::{self, Ack, DroppedMessages, Envelope, MessageReceiver};
+use crate::event::EventHub;
+use crate::panic::Panic;
+use crate::{Flow, FlushSignals, Interceptor, Middleware, ModelBase, ModelBaseReader, Signal};
+use crate::channel::LANES;
+use crate::{Backpressure, HostEvent, HostEvents, Lifecycle, PanicPolicy, Priority, Subscriptions};
 use crate::{Getter, Updater};
 use alloc::boxed::Box;
 use alloc::collections::VecDeque;
-use core::any::type_name;
-use core::ops::ControlFlow;
-use futures::StreamExt;
-use futures::channel::mpsc;
+use alloc::string::ToString;
+use core::any::{Any, TypeId, type_name};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{ControlFlow, Deref, DerefMut};
+use core::panic::AssertUnwindSafe;
+use core::task::Poll;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, AbortHandle, Abortable, OptionFuture};
+use futures::stream::{FuturesUnordered, SelectAll};
+use futures::{FutureExt, Stream, StreamExt, select_biased};
+use hashbrown::HashMap;
+
+#[cfg(feature = "persistence")]
+use crate::{
+    KeyValueStore, Migrations, PersistError, PersistPolicy, Snapshot, Storage,
+    persistence::Persister,
+};
 
 const DEFAULT_CHANNEL_BUFFER_SIZE: usize = 64;
 
-pub struct CommandQueue<A>(VecDeque<Box<dyn Command<ForApp = A>>>);
+type DynCommand<A> = Box<dyn Command<ForApp = A>>;
+
+struct Emitted<A> {
+    command: DynCommand<A>,
+    key: Option<(Key, CommandPolicy)>,
+}
+
+pub struct CommandQueue<A>(VecDeque<Emitted<A>>);
 
 impl<A: Application> CommandQueue<A> {
     pub fn emit<C: Command<ForApp = A> + 'static>(&mut self, command: C) {
-        self.0.push_back(Box::new(command));
+        self.0.push_back(Emitted {
+            command: Box::new(command),
+            key: None,
+        });
+    }
+
+    /// Emits a command which always runs as a task tracked by the host, regardless of the
+    /// [`CommandExecution`] mode. Like a concurrent command, it gets its own fork of the
+    /// [`World`], so other commands can keep using the states it doesn't hold onto across an
+    /// `.await`. `policy` decides what happens if a command with the same `key` is still in
+    /// flight.
+    pub fn emit_keyed<C: Command<ForApp = A> + 'static>(
+        &mut self,
+        key: impl Into<Key>,
+        policy: CommandPolicy,
+        command: C,
+    ) {
+        self.0.push_back(Emitted {
+            command: Box::new(command),
+            key: Some((key.into(), policy)),
+        });
     }
 }
 
 impl<A: Application> CommandQueue<A> {
-    fn pop(&mut self) -> Option<Box<dyn Command<ForApp = A>>> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Command<ForApp = A>> {
+        self.0.iter().map(|emitted| &*emitted.command)
+    }
+
+    fn pop(&mut self) -> Option<Emitted<A>> {
         self.0.pop_front()
     }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
 }
 
 impl<A> Default for CommandQueue<A> {
@@ -33,18 +100,41 @@ impl<A> Default for CommandQueue<A> {
 
 pub struct UpdateContext<'rt, A: Application> {
     pub queue: &'rt mut CommandQueue<A>,
+    pub(crate) events: &'rt EventHub,
 }
 
 impl<'rt, A: Application> UpdateContext<'rt, A> {
     pub fn emit_command<C: Command<ForApp = A> + 'static>(&mut self, command: C) {
         self.queue.emit(command);
     }
+
+    pub fn emit_keyed_command<C: Command<ForApp = A> + 'static>(
+        &mut self,
+        key: impl Into<Key>,
+        policy: CommandPolicy,
+        command: C,
+    ) {
+        self.queue.emit_keyed(key, policy, command);
+    }
+
+    #[doc(hidden)]
+    pub fn __report_error(&self, message_debug: &'static str, error: &dyn fmt::Display) {
+        tracing::warn!(message = message_debug, %error, "update failed");
+        self.events.emit(HostEvent::UpdateFailed {
+            message_debug: message_debug.into(),
+            error: error.to_string(),
+        });
+    }
 }
 
 pub struct CommandContext<'rt, A: Application> {
     pub model: ModelBaseReader<A::RootModel>,
     pub world: &'rt mut World,
+    /// Sends through the channel to the host like any other updater, so awaiting it from a
+    /// command can wait on the host, which may in turn be waiting on the command. Prefer
+    /// [`send_message`](Self::send_message).
     pub updater: Updater<A::RootModel>,
+    follow_ups: FollowUpSender<A>,
 }
 
 impl<'rt, A: Application> CommandContext<'rt, A> {
@@ -60,29 +150,251 @@ impl<'rt, A: Application> CommandContext<'rt, A> {
         self.model.get()
     }
 
-    pub fn state<S: MaybeSendSync + 'static>(&self) -> &S {
+    pub fn state<S: MaybeSendSync + 'static>(&self) -> StateRef<'_, S> {
         self.world.get()
     }
 
-    pub fn state_mut<S: MaybeSendSync + 'static>(&mut self) -> &mut S {
+    pub fn state_mut<S: MaybeSendSync + 'static>(&mut self) -> StateMut<'_, S> {
         self.world.get_mut()
     }
 
+    /// See [`World::insert`].
+    pub fn insert_state<S: MaybeSendSync + 'static>(&mut self, state: S) {
+        self.world.insert(state)
+    }
+
+    /// See [`World::remove`].
+    pub fn remove_state<S: MaybeSendSync + 'static>(&mut self) -> bool {
+        self.world.remove::<S>()
+    }
+
+    pub fn contains_state<S: MaybeSendSync + 'static>(&self) -> bool {
+        self.world.contains::<S>()
+    }
+
+    pub fn state_entry<S: MaybeSendSync + 'static>(&mut self) -> Entry<'_, S> {
+        self.world.entry()
+    }
+
+    /// Queues `message` on the host itself instead of the channel to it, so this never waits
+    /// however full the channel is. The host processes it after the commands of the current
+    /// message.
     pub async fn send_message(&mut self, message: <A::RootModel as Model>::Message) {
-        self.updater.send(message).await
+        self.send_message_with(Priority::default(), message).await
+    }
+
+    /// Sends `message` at `priority`, e.g. [`Priority::Low`] for the results of background work.
+    pub async fn send_message_with(
+        &mut self,
+        priority: Priority,
+        message: <A::RootModel as Model>::Message,
+    ) {
+        // the queue is only ever closed when the host is shutting down
+        if self.follow_ups.unbounded_send((priority, message)).is_err() {
+            tracing::debug!("host is shutting down, discarding message sent from command");
+        }
     }
 }
 
 type RootMessage<A> = <<A as Application>::RootModel as Model>::Message;
 
+// messages sent from commands, which must not wait on the channel the host may be too busy to drain
+type FollowUpSender<A> = mpsc::UnboundedSender<(Priority, RootMessage<A>)>;
+type FollowUpReceiver<A> = mpsc::UnboundedReceiver<(Priority, RootMessage<A>)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommandExecution {
+    /// Commands are awaited one after another before the host moves on to the next message.
+    #[default]
+    Sequential,
+
+    /// Commands run as tasks owned by the host, which keeps processing messages while they are
+    /// in flight. Each command gets its own fork of the [`World`], sharing every state with the
+    /// host. Borrowing a state that a command running at the same time holds onto across an
+    /// `.await` panics, like a `RefCell` would.
+    Concurrent,
+}
+
+enum Control {
+    Shutdown(oneshot::Sender<()>),
+    RemoveSource(Key),
+    Reset(oneshot::Sender<Result<(), Error>>),
+    Lifecycle(Lifecycle),
+}
+
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    control_tx: mpsc::UnboundedSender<Control>,
+}
+
+impl ShutdownHandle {
+    /// Asks the host to stop and waits until it has. Pending messages and their commands are
+    /// processed first, then every signal is flushed one last time and destroyed.
+    ///
+    /// Concurrent and keyed commands, including the keyed commands queued behind others, are
+    /// waited on until the [shutdown deadline](HostBuilder::shutdown_deadline), if there is one.
+    /// Messages they send by then are discarded. Messages held back by [`Flow::Delay`] are
+    /// cancelled instead of waited on.
+    pub async fn shutdown(&self) {
+        let (stopped_tx, stopped_rx) = oneshot::channel();
+        if self
+            .control_tx
+            .unbounded_send(Control::Shutdown(stopped_tx))
+            .is_ok()
+        {
+            // a cancelled receiver means the host was dropped, which is as stopped as it gets
+            stopped_rx.await.ok();
+        }
+    }
+}
+
+/// Removes the sources added with [`HostBuilder::source_keyed`] while the host runs.
+#[derive(Clone)]
+pub struct SourceHandle {
+    control_tx: mpsc::UnboundedSender<Control>,
+}
+
+impl SourceHandle {
+    /// Stops and drops the source added with `key`, if it hasn't ended already.
+    pub fn remove(&self, key: impl Into<Key>) {
+        // a closed channel means the host has stopped, along with every source
+        self.control_tx
+            .unbounded_send(Control::RemoveSource(key.into()))
+            .ok();
+    }
+}
+
+/// Resets a running host, see [`Host::reset`].
+#[derive(Clone)]
+pub struct ResetHandle {
+    control_tx: mpsc::UnboundedSender<Control>,
+}
+
+impl ResetHandle {
+    /// Asks the host to reset and waits until it has. Fails with [`Error::HostChannelClosed`] if
+    /// the host stops first, and like [`Host::reset`] otherwise.
+    pub async fn reset(&self) -> Result<(), Error> {
+        let (reset_tx, reset_rx) = oneshot::channel();
+        self.control_tx
+            .unbounded_send(Control::Reset(reset_tx))
+            .map_err(|_| Error::HostChannelClosed)?;
+        // a cancelled receiver means the host stopped before it got to the reset
+        reset_rx.await.map_err(|_| Error::HostChannelClosed)?
+    }
+}
+
+/// Forwards lifecycle transitions to a running host, see [`Host::set_lifecycle`].
+#[derive(Clone)]
+pub struct LifecycleHandle {
+    control_tx: mpsc::UnboundedSender<Control>,
+}
+
+impl LifecycleHandle {
+    pub fn set(&self, lifecycle: Lifecycle) {
+        // a closed channel means the host has stopped, so there is nothing left to tell
+        self.control_tx
+            .unbounded_send(Control::Lifecycle(lifecycle))
+            .ok();
+    }
+}
+
+type DeadlineFn = Box<dyn_Maybe!(SendSync Fn() -> MaybeLocalBoxFuture<'static, ()>)>;
+
+type ModelFactory<A> = dyn_Maybe!(SendSync Fn() -> <A as Application>::RootModel);
+
+type StateFactory = dyn_Maybe!(SendSync Fn() -> Slot);
+
+type SourceStream<A> = MaybeLocalBoxStream<'static, RootMessage<A>>;
+
+enum SourceItem<A: Application> {
+    Message(RootMessage<A>),
+
+    // sent once a keyed source runs out, so the host can forget it
+    Ended(Key),
+}
+
+enum TaskOutput<A: Application> {
+    Done,
+
+    Panicked(Panic),
+
+    // keyed tasks report back which command finished so the host can start the next one in line
+    Keyed(Key, u64, Option<Panic>),
+
+    // a message held back by the middleware at `resume_at - 1`
+    Delayed {
+        id: u64,
+        message: RootMessage<A>,
+        resume_at: usize,
+        acks: Vec<Ack>,
+    },
+
+    // the debounce timer of the persister ran out
+    #[cfg(feature = "persistence")]
+    Persist,
+}
+
+struct KeyedTask<A> {
+    id: u64,
+    abort: AbortHandle,
+    queued: VecDeque<DynCommand<A>>,
+}
+
+struct Queued<A: Application> {
+    coalesce_key: Option<usize>,
+    message: RootMessage<A>,
+    // a coalesced message answers for the ones it replaced as well
+    acks: Vec<Ack>,
+}
+
 pub struct Host<A: Application> {
     model: ModelBase<A::RootModel>,
     world: World,
     interceptors: Vec<Box<dyn Interceptor<A>>>,
+    middleware: Vec<Box<dyn Middleware<A>>>,
     queue: CommandQueue<A>,
+    command_execution: CommandExecution,
+    tasks: FuturesUnordered<MaybeLocalBoxFuture<'static, TaskOutput<A>>>,
+    keyed_tasks: HashMap<Key, KeyedTask<A>>,
+    // the tasks holding back delayed messages, by task id
+    delays: HashMap<u64, AbortHandle>,
+    next_task_id: u64,
+    subscriptions: HashMap<Key, AbortHandle>,
+    subscription_streams: SelectAll<Abortable<MaybeLocalBoxStream<'static, RootMessage<A>>>>,
+    sources: HashMap<Key, AbortHandle>,
+    source_streams: SelectAll<Abortable<MaybeLocalBoxStream<'static, SourceItem<A>>>>,
+    started: bool,
     signals: VecDeque<Shared<dyn FlushSignals>>,
     updater: Updater<A::RootModel>,
-    message_rx: mpsc::Receiver<RootMessage<A>>,
+    message_rx: MessageReceiver<Envelope<RootMessage<A>>>,
+    // one queue per priority lane, see `receive`
+    inbox: [VecDeque<Queued<A>>; LANES],
+    // how many messages of each lane are taken out of the channel ahead of time, so the rest
+    // keep counting against its backpressure
+    inbox_capacities: [usize; LANES],
+    // resolved by the next flush
+    acks: Vec<Ack>,
+    follow_up_tx: FollowUpSender<A>,
+    follow_up_rx: FollowUpReceiver<A>,
+    world_changes_tx: mpsc::UnboundedSender<WorldChange>,
+    world_changes_rx: mpsc::UnboundedReceiver<WorldChange>,
+    dropped_messages: DroppedMessages,
+    max_batch_size: usize,
+    control_tx: mpsc::UnboundedSender<Control>,
+    control_rx: mpsc::UnboundedReceiver<Control>,
+    stopped: Vec<oneshot::Sender<()>>,
+    shutdown_deadline: Option<DeadlineFn>,
+    panic_policy: PanicPolicy<A>,
+    events: EventHub,
+    halted: bool,
+    model_factory: Option<Box<ModelFactory<A>>>,
+    state_factories: Vec<(TypeId, Box<StateFactory>)>,
+    paused: bool,
+    suspend_when_paused: bool,
+    // the number of inbox lanes, from the highest priority down, that messages are taken from
+    open_lanes: usize,
+    #[cfg(feature = "persistence")]
+    persister: Option<Persister<A>>,
 }
 
 impl<A: Application> Host<A> {
@@ -105,49 +417,845 @@ impl<A: Application> Host<A> {
 impl<A: Application> Host<A> {
     pub async fn run(mut self) {
         tracing::debug!("host has started");
+        self.start().await;
         loop {
             if let ControlFlow::Break(()) = self.run_once().await {
                 tracing::debug!("host is stopping");
                 break;
             }
         }
+        self.stop().await;
+        tracing::debug!("host has stopped");
     }
 
     async fn run_once(&mut self) -> ControlFlow<()> {
-        match self.message_rx.next().await {
-            Some(action) => self.handle_message(action).await,
-            None => return ControlFlow::Break(()),
+        // commands in flight get polled before every message, so that a steady stream of
+        // messages can't hold them back
+        self.finish_ready_tasks().await;
+        if self.halted {
+            return ControlFlow::Break(());
+        }
+
+        // messages left in the inbox are handled without waiting, but control still comes first
+        if self.inbox[..self.open_lanes]
+            .iter()
+            .any(|lane| !lane.is_empty())
+        {
+            if let Ok(control) = self.control_rx.try_recv() {
+                return self.handle_control(control).await;
+            }
+            if let Some((message, acks)) = self.next_message() {
+                self.handle_batch(message, acks).await;
+            }
+            return self.continue_unless_halted();
+        }
+
+        // sources have no lane of their own, so they wait out a suspension altogether
+        let sources_open = !self.is_suspended();
+        select_biased! {
+            control = self.control_rx.next() => match control {
+                Some(control) => return self.handle_control(control).await,
+                None => unreachable!("the host holds a sender to its own control channel"),
+            },
+            message = self.message_rx.next() => match message {
+                Some((priority, envelope)) => {
+                    self.receive(priority, envelope);
+                    if let Some((message, acks)) = self.next_message() {
+                        self.handle_batch(message, acks).await;
+                    }
+                }
+                None => return ControlFlow::Break(()),
+            },
+            follow_up = self.follow_up_rx.next() => match follow_up {
+                Some((priority, message)) => {
+                    self.receive(priority, Envelope::new(message));
+                    if let Some((message, acks)) = self.next_message() {
+                        self.handle_batch(message, acks).await;
+                    }
+                }
+                None => unreachable!("the host holds a sender to its own follow-up queue"),
+            },
+            message = self.subscription_streams.select_next_some() => {
+                self.handle_batch(message, Vec::new()).await;
+            }
+            item = OptionFuture::from(
+                sources_open.then(|| self.source_streams.select_next_some())
+            ) => match item {
+                Some(SourceItem::Message(message)) => {
+                    self.handle_batch(message, Vec::new()).await;
+                }
+                Some(SourceItem::Ended(key)) => self.source_ended(&key),
+                None => unreachable!("the sources are only polled while the host isn't suspended"),
+            },
+            output = self.tasks.select_next_some() => self.finish_task(output).await,
+        }
+
+        self.continue_unless_halted()
+    }
+
+    fn continue_unless_halted(&self) -> ControlFlow<()> {
+        if self.halted {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    // tops up the inbox from the channel, then takes the oldest message of the highest priority
+    // lane
+    fn next_message(&mut self) -> Option<(RootMessage<A>, Vec<Ack>)> {
+        while let Ok((priority, message)) = self.follow_up_rx.try_recv() {
+            self.receive(priority, Envelope::new(message));
+        }
+        for priority in &Priority::ALL[..self.open_lanes] {
+            let lane = priority.lane();
+            while self.inbox[lane].len() < self.inbox_capacities[lane]
+                && let Some(envelope) = self.message_rx.try_recv_from(*priority)
+            {
+                self.receive(*priority, envelope);
+            }
+        }
+        self.inbox[..self.open_lanes]
+            .iter_mut()
+            .find_map(VecDeque::pop_front)
+            .map(|queued| (queued.message, queued.acks))
+    }
+
+    // a message of a coalescing variant replaces the one still waiting in its lane, if any
+    fn receive(&mut self, priority: Priority, envelope: Envelope<RootMessage<A>>) {
+        let Envelope { message, ack } = envelope;
+        let lane = &mut self.inbox[priority.lane()];
+        let coalesce_key = A::RootModel::__coalesce_key(&message, crate::__token());
+        if coalesce_key.is_some()
+            && let Some(pending) = lane.iter_mut().find(|q| q.coalesce_key == coalesce_key)
+        {
+            tracing::trace!(
+                message = A::RootModel::__describe_message(&message, crate::__token()),
+                "coalescing message"
+            );
+            pending.message = message;
+            pending.acks.extend(ack);
+            return;
+        }
+        lane.push_back(Queued {
+            coalesce_key,
+            message,
+            acks: ack.into_iter().collect(),
+        });
+    }
+
+    /// Processes the next queued message (or batch of messages, if batching is enabled) along
+    /// with its commands and signal flush. Returns the number of messages processed, which is
+    /// `0` without waiting if the queue is empty.
+    pub async fn step(&mut self) -> usize {
+        self.start().await;
+        if self.halted {
+            return 0;
+        }
+        let (message, acks) = match self.next_message() {
+            Some(next) => next,
+            None => match self.subscription_streams.next().now_or_never() {
+                Some(Some(message)) => (message, Vec::new()),
+                _ => match self.next_source_message() {
+                    Some(message) => (message, Vec::new()),
+                    None => return 0,
+                },
+            },
+        };
+        self.handle_batch(message, acks).await
+    }
+
+    /// Keeps stepping until no messages are queued and nothing is in flight (concurrent commands,
+    /// delayed messages, debounced saves), including the messages sent by commands along the way.
+    /// Returns the number of messages processed.
+    pub async fn run_until_idle(&mut self) -> usize {
+        let mut processed = 0;
+        loop {
+            match self.step().await {
+                0 if self.tasks.is_empty() || self.halted => break processed,
+                0 => {
+                    if let Some(output) = self.tasks.next().await {
+                        self.finish_task(output).await;
+                    }
+                }
+                n => processed += n,
+            }
+        }
+    }
+
+    /// Processes `message` right away, bypassing the queue.
+    pub async fn dispatch(&mut self, message: RootMessage<A>) {
+        self.start().await;
+        self.handle_message(message).await
+    }
+
+    async fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.init_model().await;
+        }
+    }
+
+    // runs `Model::init` and its commands like an update, then starts the subscriptions
+    async fn init_model(&mut self) {
+        let mut update_ctx = UpdateContext {
+            queue: &mut self.queue,
+            events: &self.events,
+        };
+        let initialized = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.model.write().init(&mut update_ctx)
+        }));
+        match initialized {
+            Ok(()) => {
+                if let PanicPolicy::Restart(checkpoint) = &mut self.panic_policy {
+                    checkpoint.capture(&self.model.read());
+                }
+                self.run_commands().await;
+            }
+            Err(payload) => {
+                self.queue.clear();
+                let message_debug = format!("{}::init", type_name::<A::RootModel>());
+                self.handle_panic(Panic::new(message_debug, payload));
+            }
+        }
+        self.sync_subscriptions();
+        self.flush_signals();
+    }
+
+    async fn handle_control(&mut self, control: Control) -> ControlFlow<()> {
+        match control {
+            Control::Shutdown(stopped_tx) => {
+                self.stopped.push(stopped_tx);
+                ControlFlow::Break(())
+            }
+            Control::RemoveSource(key) => {
+                self.remove_source(&key);
+                ControlFlow::Continue(())
+            }
+            Control::Reset(reset_tx) => {
+                reset_tx.send(self.reset().await).ok();
+                ControlFlow::Continue(())
+            }
+            Control::Lifecycle(lifecycle) => {
+                self.set_lifecycle(lifecycle);
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// Tells the host about a lifecycle transition of the application. Pausing saves the
+    /// persisted root model right away, and, if the host
+    /// [suspends when paused](HostBuilder::suspend_when_paused), stops the subscriptions, stops
+    /// polling the sources and leaves every message below [`Priority::High`] queued until the
+    /// application resumes.
+    ///
+    /// The transition is then queued as a [`Priority::High`] message for the root model's
+    /// `#[emyu(on_lifecycle)]` updater, if it has one.
+    pub fn set_lifecycle(&mut self, lifecycle: Lifecycle) {
+        tracing::debug!(?lifecycle, "lifecycle transition");
+        match lifecycle {
+            Lifecycle::Paused => {
+                self.paused = true;
+                #[cfg(feature = "persistence")]
+                if let Some(persister) = &mut self.persister {
+                    persister.save(&self.model);
+                }
+            }
+            Lifecycle::Resumed => self.paused = false,
+            _ => {}
+        }
+        self.suspend(self.paused && self.suspend_when_paused);
+        if let Some(message) = A::RootModel::__lifecycle_message(lifecycle, crate::__token()) {
+            self.receive(Priority::High, Envelope::new(message));
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn is_suspended(&self) -> bool {
+        self.open_lanes < LANES
+    }
+
+    fn suspend(&mut self, suspended: bool) {
+        self.open_lanes = if suspended { 1 } else { LANES };
+        self.message_rx.suspend(suspended);
+        self.sync_subscriptions();
+    }
+
+    /// Replaces the root model with a new one from the [factory](HostBuilder::model_factory), e.g.
+    /// on logout. Commands in flight, messages held back by [`Flow::Delay`] and subscriptions are
+    /// cancelled, then the new model is initialized with [`Model::init`] like on start. The
+    /// channel stays the same, so existing updaters and getters keep working, including zoomed
+    /// ones.
+    ///
+    /// The [`World`] is cleared as well, then refilled with the states added with
+    /// [`HostBuilder::state`] and [`HostBuilder::state_factory`]. States added with
+    /// [`HostBuilder::state_with`] or while the host was running are gone.
+    ///
+    /// Every signal of the old model is destroyed, so subscribers know to get the new one through
+    /// a getter. Messages that are already queued are applied to the new model.
+    ///
+    /// With persistence, the snapshot of the new model replaces the saved one right away, and the
+    /// signals marked `#[emyu(persist = "key")]` are loaded from the
+    /// [field store](HostBuilder::field_store) again.
+    ///
+    /// Fails with [`Error::NoModelFactory`] without changing anything if there is no factory.
+    pub async fn reset(&mut self) -> Result<(), Error> {
+        let Some(factory) = &self.model_factory else {
+            return Err(Error::NoModelFactory);
         };
+        tracing::debug!("resetting host");
+        let model = factory();
+        // nothing started for the old model may reach the new one
+        self.cancel_keyed_tasks();
+        self.cancel_delays();
+        self.tasks.clear();
+        while self.world_changes_rx.try_recv().is_ok() {}
+        for (key, abort) in self.subscriptions.drain() {
+            tracing::debug!(%key, "stopping subscription");
+            abort.abort();
+        }
+        self.world.states.clear();
+        for (type_id, factory) in &self.state_factories {
+            self.world.states.insert(*type_id, factory());
+        }
+        #[cfg(feature = "persistence")]
+        let model = {
+            let mut model = model;
+            if let Some(store) = self.world.try_get::<Box<dyn KeyValueStore>>() {
+                model.__load_fields(&**store, crate::__token());
+            }
+            model
+        };
+        let mut stale = VecDeque::new();
+        self.model
+            .__accumulate_signals(&mut stale, crate::__token());
+        *self.model.write() = model;
+        for signal in stale {
+            signal.__destroy(crate::__token());
+        }
+        if let PanicPolicy::Restart(checkpoint) = &mut self.panic_policy {
+            checkpoint.capture(&self.model.read());
+        }
+        // the snapshot of the old model must not come back on the next start
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister {
+            persister.save_now(&self.model);
+        }
+        self.init_model().await;
+        Ok(())
+    }
 
-        ControlFlow::Continue(())
+    fn add_source(&mut self, key: Option<Key>, stream: SourceStream<A>) {
+        let (abort, registration) = AbortHandle::new_pair();
+        let stream = stream.map(SourceItem::Message);
+        let stream = match key {
+            Some(key) => {
+                if let Some(replaced) = self.sources.insert(key.clone(), abort) {
+                    replaced.abort();
+                }
+                let ended = futures::stream::once(core::future::ready(SourceItem::Ended(key)));
+                crate::maybe::boxed_stream(stream.chain(ended))
+            }
+            None => crate::maybe::boxed_stream(stream),
+        };
+        self.source_streams
+            .push(Abortable::new(stream, registration));
+    }
+
+    // takes the next message a source has ready, forgetting the keyed sources that ended
+    fn next_source_message(&mut self) -> Option<RootMessage<A>> {
+        if self.is_suspended() {
+            return None;
+        }
+        loop {
+            match self.source_streams.next().now_or_never()?? {
+                SourceItem::Message(message) => return Some(message),
+                SourceItem::Ended(key) => self.source_ended(&key),
+            }
+        }
+    }
+
+    fn source_ended(&mut self, key: &Key) {
+        tracing::debug!(%key, "source has ended");
+        self.sources.remove(key);
+    }
+
+    /// Stops and drops the source added with `key`. Returns whether it was still running.
+    pub fn remove_source(&mut self, key: &Key) -> bool {
+        match self.sources.remove(key) {
+            Some(abort) => {
+                tracing::debug!(%key, "removing source");
+                abort.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn stop(&mut self) {
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister {
+            persister.close();
+        }
+        self.open_lanes = LANES;
+        self.message_rx.suspend(false);
+        self.subscriptions.clear();
+        self.subscription_streams.clear();
+        self.sources.clear();
+        self.source_streams.clear();
+        self.message_rx.close();
+        self.follow_up_rx.close();
+        while !self.halted {
+            let (message, acks) = match self.next_message() {
+                Some(next) => next,
+                None => match self.message_rx.next().await {
+                    Some((_, envelope)) => (envelope.message, envelope.ack.into_iter().collect()),
+                    None => break,
+                },
+            };
+            self.handle_batch(message, acks).await;
+        }
+        // a delayed message could only be applied by processing messages again
+        self.cancel_delays();
+        let mut deadline = match &self.shutdown_deadline {
+            Some(deadline) => deadline(),
+            None => crate::maybe::boxed_future(futures::future::pending()),
+        }
+        .fuse();
+        // checked up front since a drained `FuturesUnordered` counts as terminated, which would
+        // leave `select_biased!` waiting on the deadline alone
+        while !self.tasks.is_empty() {
+            select_biased! {
+                output = self.tasks.select_next_some() => self.finish_task(output).await,
+                () = deadline => {
+                    tracing::warn!(
+                        tasks = self.tasks.len(),
+                        "shutdown deadline has passed, dropping the commands still in flight"
+                    );
+                    self.cancel_keyed_tasks();
+                    self.tasks.clear();
+                    break;
+                }
+            }
+        }
+        self.model
+            .__accumulate_signals(&mut self.signals, crate::__token());
+        #[cfg(feature = "persistence")]
+        self.save_fields();
+        while let Some(signal) = self.signals.pop_front() {
+            signal.__flush(crate::__token());
+            signal.__destroy(crate::__token());
+        }
+        for middleware in &mut self.middleware {
+            middleware.after_flush(self.model.reader());
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister {
+            persister.save(&self.model);
+        }
+        self.control_rx.close();
+        while let Ok(control) = self.control_rx.try_recv() {
+            if let Control::Shutdown(stopped_tx) = control {
+                self.stopped.push(stopped_tx);
+            }
+        }
+        for stopped_tx in self.stopped.drain(..) {
+            stopped_tx.send(()).ok();
+        }
     }
 
     async fn handle_message(&mut self, message: RootMessage<A>) {
+        self.apply_message(message, Vec::new()).await;
+        self.sync_subscriptions();
+        self.flush_signals();
+    }
+
+    // applies up to `max_batch_size` messages that are already queued, then flushes once
+    async fn handle_batch(&mut self, first: RootMessage<A>, acks: Vec<Ack>) -> usize {
+        self.apply_message(first, acks).await;
+        let mut processed = 1;
+        while processed < self.max_batch_size
+            && !self.halted
+            && let Some((message, acks)) = self.next_message()
+        {
+            self.apply_message(message, acks).await;
+            processed += 1;
+        }
+        self.sync_subscriptions();
+        self.flush_signals();
+        processed
+    }
+
+    async fn apply_message(&mut self, message: RootMessage<A>, acks: Vec<Ack>) {
+        self.resume_message(message, 0, acks).await
+    }
+
+    // runs `message` through the middleware chain starting at `resume_at`, then applies it. The
+    // acks travel with the message until it is applied, then wait for the next flush. They are
+    // dropped along with a message that never gets applied, failing the senders waiting on them.
+    async fn resume_message(
+        &mut self,
+        mut message: RootMessage<A>,
+        resume_at: usize,
+        acks: Vec<Ack>,
+    ) {
+        self.apply_world_changes();
+        for (index, middleware) in self.middleware.iter_mut().enumerate().skip(resume_at) {
+            match middleware.before_update(self.model.reader(), message) {
+                Flow::Continue(next) => message = next,
+                Flow::Drop => return,
+                Flow::Delay(message, until) => {
+                    let id = self.next_task_id;
+                    self.next_task_id += 1;
+                    let (abort, registration) = AbortHandle::new_pair();
+                    let until = Abortable::new(until, registration);
+                    self.delays.insert(id, abort);
+                    self.tasks.push(crate::maybe::boxed_future(async move {
+                        match until.await {
+                            Ok(()) => TaskOutput::Delayed {
+                                id,
+                                message,
+                                resume_at: index + 1,
+                                acks,
+                            },
+                            Err(_) => TaskOutput::Done,
+                        }
+                    }));
+                    return;
+                }
+            }
+        }
         for interceptor in &mut self.interceptors {
             interceptor.intercept(self.model.reader(), &message);
         }
+        let message_debug = A::RootModel::__describe_message(&message, crate::__token());
         let mut update_ctx = UpdateContext {
             queue: &mut self.queue,
+            events: &self.events,
         };
-        self.model.write().update(message, &mut update_ctx);
-        self.model
-            .__accumulate_signals(&mut self.signals, crate::__token());
-        let mut command_ctx = CommandContext {
-            model: self.model.reader(),
-            world: &mut self.world,
-            updater: self.updater.clone(),
+        let updated = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.model.write().update(message, &mut update_ctx)
+        }));
+        if let Err(payload) = updated {
+            self.queue.clear();
+            self.handle_panic(Panic::new(message_debug.into(), payload));
+            return;
+        }
+        self.acks.extend(acks);
+        if let PanicPolicy::Restart(checkpoint) = &mut self.panic_policy {
+            checkpoint.capture(&self.model.read());
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister
+            && let Some(timer) = persister.touch()
+        {
+            self.tasks.push(crate::maybe::boxed_future(async move {
+                match timer.await {
+                    true => TaskOutput::Persist,
+                    false => TaskOutput::Done,
+                }
+            }));
+        }
+        for middleware in &mut self.middleware {
+            middleware.after_update(self.model.reader(), &self.queue);
+        }
+        self.run_commands().await;
+    }
+
+    async fn run_commands(&mut self) {
+        match self.command_execution {
+            CommandExecution::Sequential => self.apply_commands().await,
+            CommandExecution::Concurrent => self.spawn_commands(),
+        }
+    }
+
+    async fn apply_commands(&mut self) {
+        while let Some(Emitted { mut command, key }) = self.queue.pop() {
+            match key {
+                Some((key, policy)) => self.run_keyed(key, policy, command),
+                None => {
+                    tracing::debug!(?command, "applying command");
+                    let mut command_ctx = CommandContext {
+                        model: self.model.reader(),
+                        world: &mut self.world,
+                        updater: self.updater.clone(),
+                        follow_ups: self.follow_up_tx.clone(),
+                    };
+                    // the tasks in flight keep going while the command is awaited, but those
+                    // that finish are only handled by the loop once the batch is done
+                    let mut finished = Vec::new();
+                    let applied = {
+                        let mut apply = AssertUnwindSafe(command.apply(&mut command_ctx))
+                            .catch_unwind()
+                            .fuse();
+                        loop {
+                            select_biased! {
+                                applied = apply => break applied,
+                                output = self.tasks.select_next_some() => finished.push(output),
+                            }
+                        }
+                    };
+                    for output in finished {
+                        self.tasks
+                            .push(crate::maybe::boxed_future(future::ready(output)));
+                    }
+                    if let Err(payload) = applied {
+                        self.handle_panic(Panic::new(format!("{command:?}"), payload));
+                        if self.halted {
+                            // the remaining commands are dropped along with the queued messages
+                            self.queue.clear();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn spawn_commands(&mut self) {
+        while let Some(Emitted { command, key }) = self.queue.pop() {
+            match key {
+                Some((key, policy)) => self.run_keyed(key, policy, command),
+                None => {
+                    tracing::debug!(?command, "spawning command");
+                    let task = self.command_task(command);
+                    self.tasks.push(crate::maybe::boxed_future(async move {
+                        match task.await {
+                            Ok(()) => TaskOutput::Done,
+                            Err(panic) => TaskOutput::Panicked(panic),
+                        }
+                    }));
+                }
+            }
+        }
+    }
+
+    fn run_keyed(&mut self, key: Key, policy: CommandPolicy, command: DynCommand<A>) {
+        match (self.keyed_tasks.get_mut(&key), policy) {
+            (None, _) => {}
+            (Some(_), CommandPolicy::Exhaust) => {
+                tracing::debug!(?command, %key, "dropping command, another one is in flight");
+                return;
+            }
+            (Some(in_flight), CommandPolicy::Queue) => {
+                tracing::debug!(?command, %key, "queueing command");
+                in_flight.queued.push_back(command);
+                return;
+            }
+            (Some(in_flight), CommandPolicy::Switch) => {
+                tracing::debug!(%key, "cancelling command in flight");
+                in_flight.abort.abort();
+            }
+        }
+        let task = self.spawn_keyed(key.clone(), command);
+        self.keyed_tasks.insert(key, task);
+    }
+
+    fn spawn_keyed(&mut self, key: Key, command: DynCommand<A>) -> KeyedTask<A> {
+        tracing::debug!(?command, %key, "spawning command");
+        let id = self.next_task_id;
+        self.next_task_id += 1;
+        let (abort, registration) = AbortHandle::new_pair();
+        let task = Abortable::new(self.command_task(command), registration);
+        self.tasks.push(crate::maybe::boxed_future(async move {
+            let panic = task.await.ok().and_then(Result::err);
+            TaskOutput::Keyed(key, id, panic)
+        }));
+        KeyedTask {
+            id,
+            abort,
+            queued: VecDeque::new(),
+        }
+    }
+
+    fn command_task(
+        &self,
+        mut command: DynCommand<A>,
+    ) -> impl Future<Output = Result<(), Panic>> + MaybeSend + 'static {
+        let model = self.model.reader();
+        let mut world = self.world.fork(self.world_changes_tx.clone());
+        let updater = self.updater.clone();
+        let follow_ups = self.follow_up_tx.clone();
+        async move {
+            let mut command_ctx = CommandContext {
+                model,
+                world: &mut world,
+                updater,
+                follow_ups,
+            };
+            AssertUnwindSafe(command.apply(&mut command_ctx))
+                .catch_unwind()
+                .await
+                .map_err(|payload| Panic::new(format!("{command:?}"), payload))
+        }
+    }
+
+    // repeats the changes concurrent commands made to their forks of the world
+    fn apply_world_changes(&mut self) {
+        while let Ok(change) = self.world_changes_rx.try_recv() {
+            self.world.apply(change);
+        }
+    }
+
+    // handles the tasks that are done without waiting for the others
+    async fn finish_ready_tasks(&mut self) {
+        while let Poll::Ready(Some(output)) = futures::poll!(self.tasks.next()) {
+            self.finish_task(output).await;
+        }
+    }
+
+    async fn finish_task(&mut self, output: TaskOutput<A>) {
+        self.apply_world_changes();
+        let (key, id) = match output {
+            TaskOutput::Done => return,
+            #[cfg(feature = "persistence")]
+            TaskOutput::Persist => {
+                if let Some(persister) = &mut self.persister {
+                    persister.save(&self.model);
+                }
+                return;
+            }
+            TaskOutput::Panicked(panic) => {
+                self.handle_panic(panic);
+                return;
+            }
+            TaskOutput::Keyed(key, id, panic) => {
+                if let Some(panic) = panic {
+                    self.handle_panic(panic);
+                }
+                (key, id)
+            }
+            TaskOutput::Delayed {
+                id,
+                message,
+                resume_at,
+                acks,
+            } => {
+                self.delays.remove(&id);
+                self.resume_message(message, resume_at, acks).await;
+                self.sync_subscriptions();
+                self.flush_signals();
+                return;
+            }
         };
-        while let Some(mut command) = self.queue.pop() {
-            tracing::debug!(?command, "applying command");
-            command.apply(&mut command_ctx).await;
+        // a cancelled task still finishes, but it must not affect the one that replaced it
+        if self.keyed_tasks.get(&key).is_none_or(|task| task.id != id) {
+            return;
+        }
+        let mut finished = self
+            .keyed_tasks
+            .remove(&key)
+            .expect("task was just checked");
+        if let Some(command) = finished.queued.pop_front() {
+            let mut task = self.spawn_keyed(key.clone(), command);
+            task.queued = finished.queued;
+            self.keyed_tasks.insert(key, task);
+        }
+    }
+
+    fn cancel_keyed_tasks(&mut self) {
+        for (key, task) in self.keyed_tasks.drain() {
+            tracing::debug!(%key, "cancelling command in flight");
+            task.abort.abort();
+        }
+    }
+
+    fn cancel_delays(&mut self) {
+        for (_, abort) in self.delays.drain() {
+            abort.abort();
+        }
+    }
+
+    fn handle_panic(&mut self, panic: Panic) {
+        tracing::error!(
+            message = panic.message_debug,
+            payload = panic.payload,
+            "caught a panic"
+        );
+        self.events.emit(HostEvent::Panicked {
+            message_debug: panic.message_debug,
+            payload: panic.payload,
+        });
+        match &self.panic_policy {
+            PanicPolicy::Skip => {}
+            PanicPolicy::Restart(checkpoint) => checkpoint.restore(&mut self.model.write()),
+            PanicPolicy::Stop => self.halted = true,
+        }
+    }
+
+    fn sync_subscriptions(&mut self) {
+        // a suspended host runs no subscriptions, they are started again once it resumes
+        let subscriptions = if self.open_lanes < LANES {
+            Subscriptions::none()
+        } else {
+            self.model.read().subscriptions()
+        };
+        let mut stale = core::mem::take(&mut self.subscriptions);
+        for subscription in subscriptions {
+            let key = subscription.key().clone();
+            if let Some(abort) = stale.remove(&key) {
+                self.subscriptions.insert(key, abort);
+            } else if !self.subscriptions.contains_key(&key) {
+                tracing::debug!(%key, "starting subscription");
+                let (key, stream) = subscription.into_parts();
+                let (abort, registration) = AbortHandle::new_pair();
+                self.subscription_streams
+                    .push(Abortable::new(stream, registration));
+                self.subscriptions.insert(key, abort);
+            }
+        }
+        for (key, abort) in stale {
+            tracing::debug!(%key, "stopping subscription");
+            abort.abort();
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn save_fields(&self) {
+        if let Some(store) = self.world.try_get::<Box<dyn KeyValueStore>>() {
+            self.model
+                .read()
+                .__save_fields(&**store, crate::__token());
         }
+    }
+
+    fn flush_signals(&mut self) {
+        self.model
+            .__accumulate_signals(&mut self.signals, crate::__token());
+        #[cfg(feature = "persistence")]
+        self.save_fields();
         while let Some(signal) = self.signals.pop_front() {
             signal.__flush(crate::__token());
         }
+        for middleware in &mut self.middleware {
+            middleware.after_flush(self.model.reader());
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister {
+            persister.after_flush(&self.model);
+        }
+        for ack in self.acks.drain(..) {
+            ack.send(()).ok();
+        }
     }
 }
 
 impl<A: Application> Host<A> {
+    /// The states of the host, e.g. to inspect them between [`step`](Self::step)s in tests.
+    /// States that concurrent commands added or removed only show up here once the host processes
+    /// its next message, or after [`world_mut`](Self::world_mut).
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.apply_world_changes();
+        &mut self.world
+    }
+
     pub fn updater(&self) -> Updater<A::RootModel> {
         self.updater.clone()
     }
@@ -155,40 +1263,279 @@ impl<A: Application> Host<A> {
     pub fn getter(&self) -> Getter<A::RootModel> {
         Getter::new(self.model.clone())
     }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            control_tx: self.control_tx.clone(),
+        }
+    }
+
+    pub fn reset_handle(&self) -> ResetHandle {
+        ResetHandle {
+            control_tx: self.control_tx.clone(),
+        }
+    }
+
+    pub fn lifecycle_handle(&self) -> LifecycleHandle {
+        LifecycleHandle {
+            control_tx: self.control_tx.clone(),
+        }
+    }
+
+    pub fn source_handle(&self) -> SourceHandle {
+        SourceHandle {
+            control_tx: self.control_tx.clone(),
+        }
+    }
+
+    /// Returns a new stream of the events happening inside the host. Events are only delivered
+    /// to the streams that exist at the time they happen.
+    pub fn events(&self) -> HostEvents {
+        self.events.subscribe()
+    }
+
+    #[cfg(feature = "thread-safe")]
+    pub(crate) fn event_hub(&self) -> EventHub {
+        self.events.clone()
+    }
+
+    /// See [`Updater::dropped_messages`].
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.get()
+    }
+
+    #[cfg(feature = "thread-safe")]
+    pub(crate) fn dropped_messages_counter(&self) -> DroppedMessages {
+        self.dropped_messages.clone()
+    }
+}
+
+type DynState = dyn_Maybe!(SendSync Any);
+
+// every state has its own lock, shared between the host and the forks given to the commands
+// running alongside it, so only the commands touching the same state at the same time conflict
+#[derive(Clone)]
+struct Slot {
+    type_name: &'static str,
+    state: Shared<MaybeRwLock<Box<DynState>>>,
+}
+
+impl Slot {
+    fn new<S: MaybeSendSync + 'static>(state: S) -> Self {
+        Self {
+            type_name: type_name::<S>(),
+            state: Shared::new(MaybeRwLock::new(Box::new(state))),
+        }
+    }
+}
+
+// a change made to the fork of a concurrent command, which the host repeats on its own world
+enum WorldChange {
+    Insert(TypeId, Slot),
+    Remove(TypeId),
 }
 
 #[derive(Default)]
-pub struct World(
-    #[cfg(feature = "thread-safe")] type_map::concurrent::TypeMap,
-    #[cfg(not(feature = "thread-safe"))] type_map::TypeMap,
-);
+pub struct World {
+    states: HashMap<TypeId, Slot>,
+    // only set on forks
+    changes: Option<mpsc::UnboundedSender<WorldChange>>,
+}
 
 impl World {
     pub(crate) fn add_with<S: MaybeSendSync + 'static>(mut self, state: S) -> Self {
-        self.0.insert(state);
+        self.states.insert(TypeId::of::<S>(), Slot::new(state));
         self
     }
 
-    pub(crate) fn add<S: Default + MaybeSendSync + 'static>(self) -> Self {
-        self.add_with(S::default())
+    fn fork(&self, changes: mpsc::UnboundedSender<WorldChange>) -> Self {
+        Self {
+            states: self.states.clone(),
+            changes: Some(changes),
+        }
+    }
+
+    fn apply(&mut self, change: WorldChange) {
+        match change {
+            WorldChange::Insert(type_id, slot) => {
+                self.states.insert(type_id, slot);
+            }
+            WorldChange::Remove(type_id) => {
+                self.states.remove(&type_id);
+            }
+        }
+    }
+
+    // applies the change here, and to the host's world as well if this is a fork
+    fn change(&mut self, change: WorldChange) {
+        if let Some(changes) = &self.changes {
+            let forwarded = match &change {
+                WorldChange::Insert(type_id, slot) => WorldChange::Insert(*type_id, slot.clone()),
+                WorldChange::Remove(type_id) => WorldChange::Remove(*type_id),
+            };
+            // the host outlives every command, short of being dropped while they run
+            changes.unbounded_send(forwarded).ok();
+        }
+        self.apply(change);
+    }
+
+    /// Returns `None` if the state doesn't exist, or if a command running at the same time is
+    /// mutating it.
+    pub fn try_get<S: MaybeSendSync + 'static>(&self) -> Option<StateRef<'_, S>> {
+        let slot = self.states.get(&TypeId::of::<S>())?;
+        Some(StateRef {
+            guard: slot.state.try_read()?,
+            _state: PhantomData,
+        })
+    }
+
+    pub fn get<S: MaybeSendSync + 'static>(&self) -> StateRef<'_, S> {
+        let Some(slot) = self.states.get(&TypeId::of::<S>()) else {
+            panic!("`{}` does not exist in the world", type_name::<S>())
+        };
+        let Some(guard) = slot.state.try_read() else {
+            panic!(
+                "`{}` is being mutated by a command running at the same time",
+                type_name::<S>()
+            )
+        };
+        StateRef {
+            guard,
+            _state: PhantomData,
+        }
+    }
+
+    /// Returns `None` if the state doesn't exist, or if a command running at the same time is
+    /// holding onto it.
+    pub fn try_get_mut<S: MaybeSendSync + 'static>(&mut self) -> Option<StateMut<'_, S>> {
+        let slot = self.states.get(&TypeId::of::<S>())?;
+        Some(StateMut {
+            guard: slot.state.try_write()?,
+            _state: PhantomData,
+        })
+    }
+
+    pub fn get_mut<S: MaybeSendSync + 'static>(&mut self) -> StateMut<'_, S> {
+        let Some(slot) = self.states.get(&TypeId::of::<S>()) else {
+            panic!("`{}` does not exist in the world", type_name::<S>())
+        };
+        let Some(guard) = slot.state.try_write() else {
+            panic!(
+                "`{}` is held onto by a command running at the same time",
+                type_name::<S>()
+            )
+        };
+        StateMut {
+            guard,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn contains<S: MaybeSendSync + 'static>(&self) -> bool {
+        self.states.contains_key(&TypeId::of::<S>())
+    }
+
+    /// Adds `state`, replacing the state of the same type if there is one. Concurrent commands
+    /// get a fork of the world, in which case the host's world gets the state as well before it
+    /// processes the next message.
+    pub fn insert<S: MaybeSendSync + 'static>(&mut self, state: S) {
+        self.change(WorldChange::Insert(TypeId::of::<S>(), Slot::new(state)));
+    }
+
+    /// Removes the state of type `S`, reaching the host's world from a fork like
+    /// [`insert`](Self::insert) does. Returns whether there was one.
+    pub fn remove<S: MaybeSendSync + 'static>(&mut self) -> bool {
+        let existed = self.contains::<S>();
+        self.change(WorldChange::Remove(TypeId::of::<S>()));
+        existed
+    }
+
+    pub fn entry<S: MaybeSendSync + 'static>(&mut self) -> Entry<'_, S> {
+        Entry {
+            world: self,
+            _state: PhantomData,
+        }
+    }
+
+    /// The type names of every state, in no particular order. Only meant for debugging.
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.states.values().map(|slot| slot.type_name)
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.type_names()).finish()
+    }
+}
+
+/// A state borrowed from the [`World`]. Holding onto it across an `.await` keeps commands
+/// running at the same time from mutating the state.
+pub struct StateRef<'w, S> {
+    guard: MaybeRwLockReadGuard<'w, Box<DynState>>,
+    _state: PhantomData<&'w S>,
+}
+
+impl<S: 'static> Deref for StateRef<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        (**self.guard)
+            .downcast_ref()
+            .expect("states are keyed by their type id")
     }
+}
 
-    pub fn try_get<S: MaybeSendSync + 'static>(&self) -> Option<&S> {
-        self.0.get()
+/// A state mutably borrowed from the [`World`]. Holding onto it across an `.await` keeps
+/// commands running at the same time from using the state at all.
+pub struct StateMut<'w, S> {
+    guard: MaybeRwLockWriteGuard<'w, Box<DynState>>,
+    _state: PhantomData<&'w mut S>,
+}
+
+impl<S: 'static> Deref for StateMut<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        (**self.guard)
+            .downcast_ref()
+            .expect("states are keyed by their type id")
     }
+}
 
-    pub fn get<S: MaybeSendSync + 'static>(&self) -> &S {
-        self.try_get()
-            .unwrap_or_else(|| panic!("`{}` does not exist in the world", type_name::<S>()))
+impl<S: 'static> DerefMut for StateMut<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        (**self.guard)
+            .downcast_mut()
+            .expect("states are keyed by their type id")
     }
+}
+
+/// The state of type `S` in the [`World`], which may not exist yet.
+pub struct Entry<'w, S> {
+    world: &'w mut World,
+    _state: PhantomData<S>,
+}
 
-    pub fn try_get_mut<S: MaybeSendSync + 'static>(&mut self) -> Option<&mut S> {
-        self.0.get_mut()
+impl<'w, S: MaybeSendSync + 'static> Entry<'w, S> {
+    pub fn or_insert(self, default: S) -> StateMut<'w, S> {
+        self.or_insert_with(|| default)
     }
 
-    pub fn get_mut<S: MaybeSendSync + 'static>(&mut self) -> &mut S {
-        self.try_get_mut()
-            .unwrap_or_else(|| panic!("`{}` does not exist in the world", type_name::<S>()))
+    /// Inserts the state returned by `default` if there is none, then gets it like
+    /// [`World::get_mut`].
+    pub fn or_insert_with(self, default: impl FnOnce() -> S) -> StateMut<'w, S> {
+        if !self.world.contains::<S>() {
+            self.world.insert(default());
+        }
+        self.world.get_mut()
+    }
+
+    pub fn or_default(self) -> StateMut<'w, S>
+    where
+        S: Default,
+    {
+        self.or_insert_with(S::default)
     }
 }
 
@@ -196,7 +1543,22 @@ pub struct HostBuilder<A: Application> {
     model: Option<A::RootModel>,
     world: World,
     interceptors: Vec<Box<dyn Interceptor<A>>>,
+    middleware: Vec<Box<dyn Middleware<A>>>,
     buffer_size: usize,
+    lane_buffer_sizes: [Option<usize>; LANES],
+    backpressure: Backpressure,
+    max_batch_size: usize,
+    command_execution: CommandExecution,
+    panic_policy: PanicPolicy<A>,
+    sources: Vec<(Option<Key>, SourceStream<A>)>,
+    model_factory: Option<Box<ModelFactory<A>>>,
+    state_factories: Vec<(TypeId, Box<StateFactory>)>,
+    suspend_when_paused: bool,
+    shutdown_deadline: Option<DeadlineFn>,
+    #[cfg(feature = "persistence")]
+    persister: Option<Persister<A>>,
+    #[cfg(feature = "persistence")]
+    migrations: Migrations,
 }
 
 impl<A: Application> HostBuilder<A> {
@@ -218,6 +1580,8 @@ impl<A: Application> HostBuilder<A> {
         }
     }
 
+    /// Adds `value` to the world. [`Host::reset`] removes it, see
+    /// [`state_factory`](Self::state_factory) for states that should be recreated instead.
     pub fn state_with<S: MaybeSendSync + 'static>(self, value: S) -> Self {
         Self {
             world: self.world.add_with(value),
@@ -225,11 +1589,21 @@ impl<A: Application> HostBuilder<A> {
         }
     }
 
+    /// Adds `S::default()` to the world, which is also what [`Host::reset`] resets it to.
     pub fn state<S: Default + MaybeSendSync + 'static>(self) -> Self {
-        Self {
-            world: self.world.add::<S>(),
-            ..self
-        }
+        self.state_factory(S::default)
+    }
+
+    /// Adds the state returned by `factory` to the world, and calls it again to recreate the
+    /// state whenever the host is [reset](Host::reset).
+    pub fn state_factory<S: MaybeSendSync + 'static>(
+        mut self,
+        factory: impl Fn() -> S + MaybeSendSync + 'static,
+    ) -> Self {
+        self.world = self.world.add_with(factory());
+        self.state_factories
+            .push((TypeId::of::<S>(), Box::new(move || Slot::new(factory()))));
+        self
     }
 
     pub fn interceptor(mut self, value: impl Interceptor<A>) -> Self {
@@ -237,6 +1611,12 @@ impl<A: Application> HostBuilder<A> {
         self
     }
 
+    /// Appends `value` to the middleware chain. Middleware run in the order they were added.
+    pub fn middleware(mut self, value: impl Middleware<A>) -> Self {
+        self.middleware.push(Box::new(value));
+        self
+    }
+
     pub fn buffer_size(self, value: usize) -> Self {
         Self {
             buffer_size: value,
@@ -244,6 +1624,144 @@ impl<A: Application> HostBuilder<A> {
         }
     }
 
+    /// Overrides [`buffer_size`](Self::buffer_size) for the lane of `priority`.
+    pub fn lane_buffer_size(mut self, priority: Priority, value: usize) -> Self {
+        self.lane_buffer_sizes[priority.lane()] = Some(value);
+        self
+    }
+
+    /// Decides what happens once a lane of the channel to the host holds
+    /// [`buffer_size`](Self::buffer_size) messages. Defaults to [`Backpressure::Block`].
+    pub fn backpressure(self, value: Backpressure) -> Self {
+        Self {
+            backpressure: value,
+            ..self
+        }
+    }
+
+    /// Applies up to `max_batch_size` already queued messages before flushing signals, so
+    /// subscribers are woken once per burst instead of once per message. A size of `1` (the
+    /// default) disables batching.
+    pub fn batching(self, max_batch_size: usize) -> Self {
+        Self {
+            max_batch_size: max_batch_size.max(1),
+            ..self
+        }
+    }
+
+    pub fn command_execution(self, value: CommandExecution) -> Self {
+        Self {
+            command_execution: value,
+            ..self
+        }
+    }
+
+    /// Restores the root model from the snapshot in `storage` when the host is built, and saves
+    /// a new snapshot after updates according to `policy`. The model given to
+    /// [`model`](Self::model) is the one the snapshot is restored into.
+    #[cfg(feature = "persistence")]
+    pub fn persist(self, storage: impl Storage, policy: PersistPolicy) -> Self
+    where
+        A::RootModel: Snapshot,
+        <A::RootModel as Snapshot>::Snapshot: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        Self {
+            persister: Some(Persister::new(storage, policy)),
+            ..self
+        }
+    }
+
+    /// Adds `store` to the world as a `Box<dyn KeyValueStore>`. The signals marked with
+    /// `#[emyu(persist = "key")]` are loaded from it when the host is built, and written back to
+    /// it whenever they change.
+    #[cfg(feature = "persistence")]
+    pub fn field_store(self, store: impl KeyValueStore) -> Self {
+        // shared so that a reset hands the same store back to the new world
+        let store: Shared<dyn KeyValueStore> = Shared::new(store);
+        self.state_factory::<Box<dyn KeyValueStore>>(move || Box::new(Shared::clone(&store)))
+    }
+
+    /// Sets the migrations that bring snapshots saved by older versions of the application up to
+    /// date. Only has an effect along with [`persist`](Self::persist).
+    #[cfg(feature = "persistence")]
+    pub fn migrations(self, value: Migrations) -> Self {
+        Self {
+            migrations: value,
+            ..self
+        }
+    }
+
+    /// Suspends the host while the application is [paused](Lifecycle::Paused): only
+    /// [`Priority::High`] messages are processed, subscriptions are stopped and sources aren't
+    /// polled until it resumes. Disabled by default.
+    pub fn suspend_when_paused(self, value: bool) -> Self {
+        Self {
+            suspend_when_paused: value,
+            ..self
+        }
+    }
+
+    /// Limits how long stopping the host waits on the concurrent and keyed commands still in
+    /// flight to as long as the future returned by `deadline` takes to complete, e.g.
+    /// `|| tokio::time::sleep(Duration::from_secs(2))`. Commands still running or queued by then
+    /// are dropped. Without a deadline, the host waits for all of them.
+    pub fn shutdown_deadline<F, Fut>(self, deadline: F) -> Self
+    where
+        F: Fn() -> Fut + MaybeSendSync + 'static,
+        Fut: Future<Output = ()> + MaybeSend + 'static,
+    {
+        Self {
+            shutdown_deadline: Some(Box::new(move || crate::maybe::boxed_future(deadline()))),
+            ..self
+        }
+    }
+
+    /// Decides what happens after an update or a command panics. Defaults to
+    /// [`PanicPolicy::Skip`].
+    pub fn on_panic(self, value: PanicPolicy<A>) -> Self {
+        Self {
+            panic_policy: value,
+            ..self
+        }
+    }
+
+    /// Merges the items of `stream`, turned into root messages by `map`, into the messages the
+    /// host processes. The source is dropped once the stream ends.
+    pub fn source<S, F>(mut self, stream: S, map: F) -> Self
+    where
+        S: Stream + MaybeSend + 'static,
+        F: FnMut(S::Item) -> RootMessage<A> + MaybeSend + 'static,
+    {
+        let stream = crate::maybe::boxed_stream(stream.map(map));
+        self.sources.push((None, stream));
+        self
+    }
+
+    /// Like [`source`](Self::source), but the source can be removed early through
+    /// [`Host::remove_source`] or a [`SourceHandle`]. Adding another source with the same `key`
+    /// replaces it.
+    pub fn source_keyed<S, F>(mut self, key: impl Into<Key>, stream: S, map: F) -> Self
+    where
+        S: Stream + MaybeSend + 'static,
+        F: FnMut(S::Item) -> RootMessage<A> + MaybeSend + 'static,
+    {
+        let stream = crate::maybe::boxed_stream(stream.map(map));
+        self.sources.push((Some(key.into()), stream));
+        self
+    }
+
+    /// Sets the factory [`Host::reset`] creates the new root model with. It also creates the
+    /// initial model if [`model`](Self::model) isn't given.
+    pub fn model_factory(
+        self,
+        factory: impl Fn() -> A::RootModel + MaybeSendSync + 'static,
+    ) -> Self {
+        Self {
+            model_factory: Some(Box::new(factory)),
+            ..self
+        }
+    }
+
     pub fn default_model(self) -> Self
     where
         A::RootModel: Default,
@@ -251,21 +1769,115 @@ impl<A: Application> HostBuilder<A> {
         self.model(Default::default())
     }
 
-    pub fn build(self) -> Host<A> {
-        let model = self.model.expect("RootModel was not initialized");
+    /// Builds the host. If the root model is persisted and restoring it fails, the host falls
+    /// back to the model given to [`model`](Self::model).
+    pub fn build(mut self) -> Host<A> {
+        let model = self.take_model();
+        #[cfg(feature = "persistence")]
+        let model = {
+            let mut model = model;
+            if let Some(persister) = &self.persister
+                && let Err(error) = persister.restore(&mut model)
+            {
+                tracing::warn!(%error, "failed to restore the root model, using the given one");
+            }
+            model
+        };
+        self.assemble(model)
+    }
+
+    /// Like [`build`](Self::build), but fails if the root model can't be restored.
+    #[cfg(feature = "persistence")]
+    pub fn try_build(mut self) -> Result<Host<A>, PersistError> {
+        let mut model = self.take_model();
+        if let Some(persister) = &self.persister {
+            persister.restore(&mut model)?;
+        }
+        Ok(self.assemble(model))
+    }
+
+    fn take_model(&mut self) -> A::RootModel {
+        #[cfg(feature = "persistence")]
+        if let Some(persister) = &mut self.persister {
+            persister.migrations = core::mem::take(&mut self.migrations);
+        }
+        self.model
+            .take()
+            .or_else(|| self.model_factory.as_ref().map(|factory| factory()))
+            .expect("RootModel was not initialized")
+    }
+
+    fn assemble(self, model: A::RootModel) -> Host<A> {
+        #[cfg(feature = "persistence")]
+        let model = {
+            let mut model = model;
+            if let Some(store) = self.world.try_get::<Box<dyn KeyValueStore>>() {
+                model.__load_fields(&**store, crate::__token());
+            }
+            model
+        };
+        let mut panic_policy = self.panic_policy;
+        if let PanicPolicy::Restart(checkpoint) = &mut panic_policy {
+            checkpoint.capture(&model);
+        }
         let model = ModelBase::new(model);
 
-        let (message_tx, message_rx) = mpsc::channel(self.buffer_size);
+        let capacities = self
+            .lane_buffer_sizes
+            .map(|size| size.unwrap_or(self.buffer_size));
+        let (message_tx, message_rx) = channel::channel(capacities, self.backpressure);
+        let dropped_messages = message_tx.dropped_messages();
+        let (control_tx, control_rx) = mpsc::unbounded();
+        let (follow_up_tx, follow_up_rx) = mpsc::unbounded();
+        let (world_changes_tx, world_changes_rx) = mpsc::unbounded();
 
-        Host {
+        let mut host = Host {
             model: model.clone(),
             world: self.world,
             interceptors: self.interceptors,
+            middleware: self.middleware,
             queue: CommandQueue::default(),
+            command_execution: self.command_execution,
+            tasks: FuturesUnordered::new(),
+            keyed_tasks: HashMap::new(),
+            delays: HashMap::new(),
+            next_task_id: 0,
+            subscriptions: HashMap::new(),
+            subscription_streams: SelectAll::new(),
+            sources: HashMap::new(),
+            source_streams: SelectAll::new(),
+            started: false,
             signals: VecDeque::new(),
             updater: Updater::new(message_tx),
+            dropped_messages,
             message_rx,
+            inbox: Default::default(),
+            inbox_capacities: capacities.map(|capacity| capacity.max(1)),
+            acks: Vec::new(),
+            follow_up_tx,
+            follow_up_rx,
+            world_changes_tx,
+            world_changes_rx,
+            max_batch_size: self.max_batch_size,
+            control_tx,
+            control_rx,
+            stopped: Vec::new(),
+            shutdown_deadline: self.shutdown_deadline,
+            panic_policy,
+            events: EventHub::default(),
+            halted: false,
+            model_factory: self.model_factory,
+            state_factories: self.state_factories,
+            paused: false,
+            suspend_when_paused: self.suspend_when_paused,
+            open_lanes: LANES,
+            #[cfg(feature = "persistence")]
+            persister: self.persister,
+        };
+        for (key, stream) in self.sources {
+            host.add_source(key, stream);
         }
+        host
     }
 }
 
@@ -275,7 +1887,22 @@ impl<A: Application> Default for HostBuilder<A> {
             model: None,
             world: World::default(),
             interceptors: Vec::new(),
+            middleware: Vec::new(),
             buffer_size: DEFAULT_CHANNEL_BUFFER_SIZE,
+            lane_buffer_sizes: [None; LANES],
+            backpressure: Backpressure::default(),
+            max_batch_size: 1,
+            command_execution: CommandExecution::default(),
+            panic_policy: PanicPolicy::default(),
+            sources: Vec::new(),
+            model_factory: None,
+            state_factories: Vec::new(),
+            suspend_when_paused: false,
+            shutdown_deadline: None,
+            #[cfg(feature = "persistence")]
+            persister: None,
+            #[cfg(feature = "persistence")]
+            migrations: Migrations::new(),
         }
     }
 }
@@ -26,6 +26,9 @@ pub mod __macros {
 
     pub use crate::FlushSignals;
     pub use crate::maybe::Shared;
+
+    #[cfg(feature = "persistence")]
+    pub use crate::persistence::{__load_field, __save_field};
 }
 
 #[doc(hidden)]
@@ -51,19 +54,41 @@ pub mod base;
 pub mod dispatcher;
 
 pub mod host;
+pub mod channel;
 pub mod command;
+pub mod event;
+pub mod history;
+pub mod lifecycle;
+pub mod middleware;
+pub mod panic;
+pub mod reply;
+pub mod subscription;
 
 #[cfg(feature = "thread-safe")]
 pub mod handle;
 
+#[cfg(feature = "persistence")]
+pub mod persistence;
+
 pub use base::*;
 pub use dispatcher::*;
 pub use host::*;
+pub use channel::*;
 pub use command::*;
+pub use event::*;
+pub use history::*;
+pub use lifecycle::*;
+pub use middleware::*;
+pub use panic::*;
+pub use reply::*;
+pub use subscription::*;
 
 #[cfg(feature = "thread-safe")]
 pub use handle::*;
 
+#[cfg(feature = "persistence")]
+pub use persistence::*;
+
 #[doc(hidden)]
 pub fn __token() -> __private::Token {
     __private::Token::new()
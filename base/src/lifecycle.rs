@@ -0,0 +1,17 @@
+/// A lifecycle transition of the application, as reported by the platform. The host is told
+/// about them through [`Host::set_lifecycle`](crate::Host::set_lifecycle) or a
+/// [`LifecycleHandle`](crate::LifecycleHandle), and hands them to the root model's
+/// `#[emyu(on_lifecycle)]` updater, if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Lifecycle {
+    /// The application is back in the foreground.
+    Resumed,
+
+    /// The application went to the background, where the platform may kill it without further
+    /// notice.
+    Paused,
+
+    /// The platform is running low on memory, so caches should be dropped.
+    LowMemory,
+}
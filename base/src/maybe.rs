@@ -9,6 +9,21 @@ mod impls {
     impl<T: 'static> MaybeStatic for T {}
     pub type Shared<T> = alloc::sync::Arc<T>;
     pub type MaybeLocalBoxFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+    pub type MaybeLocalBoxStream<'a, T> = futures::stream::BoxStream<'a, T>;
+
+    pub fn boxed_future<'a, F>(future: F) -> MaybeLocalBoxFuture<'a, F::Output>
+    where
+        F: Future + Send + 'a,
+    {
+        alloc::boxed::Box::pin(future)
+    }
+
+    pub fn boxed_stream<'a, S>(stream: S) -> MaybeLocalBoxStream<'a, S::Item>
+    where
+        S: futures::Stream + Send + 'a,
+    {
+        alloc::boxed::Box::pin(stream)
+    }
 }
 
 #[cfg(not(feature = "thread-safe"))]
@@ -22,9 +37,27 @@ mod impls {
     impl<T> MaybeStatic for T {}
     pub type Shared<T> = alloc::rc::Rc<T>;
     pub type MaybeLocalBoxFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
+    pub type MaybeLocalBoxStream<'a, T> = futures::stream::LocalBoxStream<'a, T>;
+
+    pub fn boxed_future<'a, F>(future: F) -> MaybeLocalBoxFuture<'a, F::Output>
+    where
+        F: Future + 'a,
+    {
+        alloc::boxed::Box::pin(future)
+    }
+
+    pub fn boxed_stream<'a, S>(stream: S) -> MaybeLocalBoxStream<'a, S::Item>
+    where
+        S: futures::Stream + 'a,
+    {
+        alloc::boxed::Box::pin(stream)
+    }
 }
 
-pub use impls::{MaybeSend, MaybeStatic, MaybeSync, Shared, MaybeLocalBoxFuture};
+pub use impls::{
+    MaybeLocalBoxFuture, MaybeLocalBoxStream, MaybeSend, MaybeStatic, MaybeSync, Shared,
+    boxed_future, boxed_stream,
+};
 
 #[cfg(feature = "thread-safe")]
 mod sync {
@@ -41,11 +74,31 @@ mod sync {
         RwLockReadGuard as RwLockReadGuardImpl, RwLockWriteGuard as RwLockWriteGuardImpl,
     };
 
+    // a panic caught by the host while a lock is held must not take the lock down with it
     macro_rules! unwrap_lock {
         ($e:expr) => {{
             #[cfg(feature = "std")]
             {
-                $e.unwrap()
+                $e.unwrap_or_else(std::sync::PoisonError::into_inner)
+            }
+
+            #[cfg(not(feature = "std"))]
+            {
+                $e
+            }
+        }};
+    }
+
+    // like `unwrap_lock!`, but `None` if the lock is held elsewhere
+    macro_rules! try_lock {
+        ($e:expr) => {{
+            #[cfg(feature = "std")]
+            {
+                match $e {
+                    Ok(guard) => Some(guard),
+                    Err(std::sync::TryLockError::Poisoned(error)) => Some(error.into_inner()),
+                    Err(std::sync::TryLockError::WouldBlock) => None,
+                }
             }
 
             #[cfg(not(feature = "std"))]
@@ -70,6 +123,12 @@ mod sync {
         pub fn write(&self) -> MaybeRwLockWriteGuard<'_, T> {
             MaybeRwLockWriteGuard(unwrap_lock!(self.0.write()))
         }
+        pub fn try_read(&self) -> Option<MaybeRwLockReadGuard<'_, T>> {
+            try_lock!(self.0.try_read()).map(MaybeRwLockReadGuard)
+        }
+        pub fn try_write(&self) -> Option<MaybeRwLockWriteGuard<'_, T>> {
+            try_lock!(self.0.try_write()).map(MaybeRwLockWriteGuard)
+        }
     }
 
     pub struct MaybeMutex<T>(MutexImpl<T>);
@@ -132,6 +191,12 @@ mod sync {
         pub fn write(&self) -> MaybeRwLockWriteGuard<'_, T> {
             self.0.borrow_mut()
         }
+        pub fn try_read(&self) -> Option<MaybeRwLockReadGuard<'_, T>> {
+            self.0.try_borrow().ok()
+        }
+        pub fn try_write(&self) -> Option<MaybeRwLockWriteGuard<'_, T>> {
+            self.0.try_borrow_mut().ok()
+        }
     }
 
     pub struct MaybeMutex<T>(RefCell<T>);
@@ -147,9 +212,7 @@ mod sync {
     }
 }
 
-pub use sync::{
-    MaybeMutex, MaybeMutexGuard, MaybeRwLock, MaybeRwLockReadGuard, MaybeRwLockWriteGuard,
-};
+pub use sync::{MaybeMutex, MaybeRwLock, MaybeRwLockReadGuard, MaybeRwLockWriteGuard};
 
 pub trait MaybeSendSync: MaybeSend + MaybeSync {}
 impl<T: MaybeSend + MaybeSync> MaybeSendSync for T {}
@@ -0,0 +1,52 @@
+use crate::maybe::{MaybeLocalBoxFuture, MaybeSend, MaybeSendSync};
+use crate::{Application, CommandQueue, Model, ModelBaseReader};
+
+type RootMessage<A> = <<A as Application>::RootModel as Model>::Message;
+
+/// What the host should do with a message after it went through [`Middleware::before_update`].
+pub enum Flow<A: Application> {
+    /// Passes the message, possibly replaced, on to the next middleware in the chain.
+    Continue(RootMessage<A>),
+
+    /// Discards the message. Later middleware and the model never see it.
+    Drop,
+
+    /// Holds the message back until the future completes, then resumes the chain right after
+    /// the middleware that delayed it. The host keeps processing other messages in the meantime.
+    Delay(RootMessage<A>, MaybeLocalBoxFuture<'static, ()>),
+}
+
+impl<A: Application> Flow<A> {
+    pub fn delay(
+        message: RootMessage<A>,
+        until: impl Future<Output = ()> + MaybeSend + 'static,
+    ) -> Self {
+        Self::Delay(message, crate::maybe::boxed_future(until))
+    }
+}
+
+/// Hooks into the message pipeline of the host. Middleware run in the order they were added to
+/// the [`HostBuilder`](crate::HostBuilder).
+pub trait Middleware<A: Application>: MaybeSendSync + 'static {
+    /// Called before the message reaches the model. Interceptors only see the messages that make
+    /// it through every middleware.
+    fn before_update(
+        &mut self,
+        model: ModelBaseReader<A::RootModel>,
+        message: RootMessage<A>,
+    ) -> Flow<A> {
+        let _ = model;
+        Flow::Continue(message)
+    }
+
+    /// Called after the model was updated, with the commands the update emitted. The commands
+    /// have not run yet.
+    fn after_update(&mut self, model: ModelBaseReader<A::RootModel>, commands: &CommandQueue<A>) {
+        let _ = (model, commands);
+    }
+
+    /// Called after the signals changed by an update (or a batch of updates) were flushed.
+    fn after_flush(&mut self, model: ModelBaseReader<A::RootModel>) {
+        let _ = model;
+    }
+}
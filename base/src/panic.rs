@@ -0,0 +1,90 @@
+use crate::maybe::MaybeSendSync;
+use crate::{Application, Snapshot};
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::any::Any;
+
+type DynSnapshot = dyn_Maybe!(SendSync Any);
+
+/// What the host does after catching a panic from [`Model::update`](crate::Model::update) or
+/// [`Command::apply`](crate::Command::apply). Either way, a
+/// [`HostEvent::Panicked`](crate::HostEvent::Panicked) is emitted first.
+pub enum PanicPolicy<A: Application> {
+    /// Drops the message, along with any commands it emitted, and carries on. Whatever the update
+    /// changed before panicking stays changed.
+    Skip,
+
+    /// Like [`Skip`](Self::Skip), but also puts the root model back to how it was after the last
+    /// update that didn't panic. Created with [`PanicPolicy::restart`].
+    Restart(Checkpoint<A>),
+
+    /// Stops the host, as if it was shut down, without processing the queued messages.
+    Stop,
+}
+
+impl<A: Application> PanicPolicy<A> {
+    /// Snapshots the root model after every update, so it can be restored after a panic.
+    pub fn restart() -> Self
+    where
+        A::RootModel: Snapshot,
+        <A::RootModel as Snapshot>::Snapshot: Clone + MaybeSendSync + 'static,
+    {
+        Self::Restart(Checkpoint {
+            capture: |model| Box::new(model.snapshot()),
+            restore: |model, snapshot| {
+                let snapshot = snapshot
+                    .downcast_ref::<<A::RootModel as Snapshot>::Snapshot>()
+                    .expect("checkpoints are captured from the same model");
+                model.restore(snapshot.clone());
+            },
+            last_good: None,
+        })
+    }
+}
+
+// deriving would require `A: Default`
+#[allow(clippy::derivable_impls)]
+impl<A: Application> Default for PanicPolicy<A> {
+    fn default() -> Self {
+        Self::Skip
+    }
+}
+
+pub struct Checkpoint<A: Application> {
+    capture: fn(&A::RootModel) -> Box<DynSnapshot>,
+    restore: fn(&mut A::RootModel, &DynSnapshot),
+    last_good: Option<Box<DynSnapshot>>,
+}
+
+impl<A: Application> Checkpoint<A> {
+    pub(crate) fn capture(&mut self, model: &A::RootModel) {
+        self.last_good = Some((self.capture)(model));
+    }
+
+    pub(crate) fn restore(&self, model: &mut A::RootModel) {
+        if let Some(snapshot) = &self.last_good {
+            (self.restore)(model, &**snapshot);
+        }
+    }
+}
+
+pub(crate) struct Panic {
+    pub(crate) message_debug: String,
+    pub(crate) payload: String,
+}
+
+impl Panic {
+    pub(crate) fn new(message_debug: String, payload: Box<dyn Any + Send>) -> Self {
+        let payload = match payload.downcast::<&'static str>() {
+            Ok(payload) => String::from(*payload),
+            Err(payload) => match payload.downcast::<String>() {
+                Ok(payload) => *payload,
+                Err(_) => String::from("Box<dyn Any>"),
+            },
+        };
+        Self {
+            message_debug,
+            payload,
+        }
+    }
+}
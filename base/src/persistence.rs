@@ -0,0 +1,349 @@
+use crate::maybe::{MaybeLocalBoxFuture, MaybeSend, MaybeSendSync, Shared};
+use crate::{Application, ModelBase, Signal, Snapshot};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::error::Error as StdError;
+use futures::future::{AbortHandle, Abortable};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PersistError {
+    #[error("failed to access the storage: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to (de)serialize the snapshot: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("the snapshot is at version {found}, but the latest known version is {latest}")]
+    UnknownVersion { found: u32, latest: u32 },
+
+    #[error("failed to migrate the snapshot from version {from} to {}: {source}", from + 1)]
+    Migration {
+        from: u32,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+}
+
+/// Where the snapshot of the root model is kept between runs.
+pub trait Storage: MaybeSendSync + 'static {
+    /// Loads the last stored snapshot, or `None` if nothing was stored yet.
+    fn load(&self) -> Result<Option<Vec<u8>>, PersistError>;
+
+    fn store(&mut self, data: &[u8]) -> Result<(), PersistError>;
+}
+
+/// Stores the snapshot in a single file.
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> Result<Option<Vec<u8>>, PersistError> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn store(&mut self, data: &[u8]) -> Result<(), PersistError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // write to a temporary file first so a crash mid-write never leaves a torn snapshot
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, data)?;
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Stores values by key, for the signals marked with `#[emyu(persist = "key")]`. The host looks
+/// for the store in the [`World`](crate::World), see
+/// [`HostBuilder::field_store`](crate::HostBuilder::field_store).
+pub trait KeyValueStore: MaybeSendSync + 'static {
+    fn load(&self, key: &str) -> Result<Option<Value>, PersistError>;
+
+    fn store(&self, key: &str, value: Value) -> Result<(), PersistError>;
+}
+
+impl<S: KeyValueStore + ?Sized> KeyValueStore for Shared<S> {
+    fn load(&self, key: &str) -> Result<Option<Value>, PersistError> {
+        (**self).load(key)
+    }
+
+    fn store(&self, key: &str, value: Value) -> Result<(), PersistError> {
+        (**self).store(key, value)
+    }
+}
+
+/// Stores every value in its own `<key>.json` file inside a directory, so keys must be valid
+/// file names.
+pub struct FileKeyValueStore {
+    dir: PathBuf,
+}
+
+impl FileKeyValueStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        Path::new(&self.dir).join(format!("{key}.json"))
+    }
+}
+
+impl KeyValueStore for FileKeyValueStore {
+    fn load(&self, key: &str) -> Result<Option<Value>, PersistError> {
+        match FileStorage::new(self.path(key)).load()? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn store(&self, key: &str, value: Value) -> Result<(), PersistError> {
+        FileStorage::new(self.path(key)).store(&serde_json::to_vec(&value)?)
+    }
+}
+
+#[doc(hidden)]
+pub fn __load_field<T: DeserializeOwned>(store: &dyn KeyValueStore, key: &str, signal: &Signal<T>) {
+    let result = store
+        .load(key)
+        .and_then(|value| Ok(value.map(serde_json::from_value).transpose()?));
+    match result {
+        Ok(Some(value)) => signal.writer().set(value),
+        Ok(None) => {}
+        Err(error) => tracing::warn!(%error, key, "failed to load persisted field"),
+    }
+}
+
+// called right before the signals are flushed, while the dirty flag is still set
+#[doc(hidden)]
+pub fn __save_field<T: Serialize>(store: &dyn KeyValueStore, key: &str, signal: &Signal<T>) {
+    if !signal.is_dirty() {
+        return;
+    }
+    let result = serde_json::to_value(&*signal.reader().read())
+        .map_err(PersistError::from)
+        .and_then(|value| store.store(key, value));
+    if let Err(error) = result {
+        tracing::error!(%error, key, "failed to save persisted field");
+    }
+}
+
+type MigrationFn =
+    Box<dyn_Maybe!(SendSync Fn(Value) -> Result<Value, Box<dyn StdError + Send + Sync>>)>;
+
+/// The chain of migrations that brings an older snapshot up to date before it is deserialized.
+///
+/// Snapshots are stored along with their version, which is the number of migrations known when
+/// they were saved. Snapshots saved before any migration existed are at version `0`.
+#[derive(Default)]
+pub struct Migrations(Vec<MigrationFn>);
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the migration from the latest version to the next one, so the first call adds
+    /// the migration from version `0` to `1`, the second from `1` to `2`, and so on.
+    pub fn step<F, E>(mut self, migration: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value, E> + MaybeSendSync + 'static,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        self.0
+            .push(Box::new(move |value| migration(value).map_err(Into::into)));
+        self
+    }
+
+    /// The version snapshots are saved at.
+    pub fn latest(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    fn migrate(&self, mut value: Value, from: u32) -> Result<Value, PersistError> {
+        let latest = self.latest();
+        if from > latest {
+            return Err(PersistError::UnknownVersion {
+                found: from,
+                latest,
+            });
+        }
+        for (version, migration) in (from..).zip(&self.0[from as usize..]) {
+            tracing::debug!(from = version, "migrating snapshot");
+            value = migration(value).map_err(|source| PersistError::Migration {
+                from: version,
+                source,
+            })?;
+        }
+        Ok(value)
+    }
+}
+
+// prefixed so that an unversioned snapshot with fields of the same names is never mistaken for an
+// envelope
+const VERSION_FIELD: &str = "__emyu_version";
+const STATE_FIELD: &str = "__emyu_state";
+
+fn wrap(version: u32, state: Value) -> Value {
+    let mut envelope = Map::new();
+    envelope.insert(VERSION_FIELD.into(), version.into());
+    envelope.insert(STATE_FIELD.into(), state);
+    Value::Object(envelope)
+}
+
+// anything that isn't an envelope was saved before snapshots were versioned
+fn unwrap(value: Value) -> (u32, Value) {
+    match value {
+        Value::Object(mut envelope)
+            if envelope.len() == 2
+                && envelope.contains_key(STATE_FIELD)
+                && let Some(version) = envelope.get(VERSION_FIELD).and_then(Value::as_u64)
+                && let Ok(version) = u32::try_from(version) =>
+        {
+            let state = envelope
+                .remove(STATE_FIELD)
+                .expect("field was just checked");
+            (version, state)
+        }
+        value => (0, value),
+    }
+}
+
+type DelayFn = Box<dyn_Maybe!(SendSync Fn() -> MaybeLocalBoxFuture<'static, ()>)>;
+
+/// When the host saves the snapshot of the root model.
+pub enum PersistPolicy {
+    /// Saves after every update, or after every batch if batching is enabled.
+    EveryUpdate,
+
+    /// Saves once no update happened for as long as the future returned by the function takes to
+    /// complete, e.g. `|| tokio::time::sleep(Duration::from_millis(500))`.
+    Debounce(DelayFn),
+}
+
+impl PersistPolicy {
+    pub fn debounce<F, Fut>(delay: F) -> Self
+    where
+        F: Fn() -> Fut + MaybeSendSync + 'static,
+        Fut: Future<Output = ()> + MaybeSend + 'static,
+    {
+        Self::Debounce(Box::new(move || crate::maybe::boxed_future(delay())))
+    }
+}
+
+pub(crate) struct Persister<A: Application> {
+    storage: Box<dyn Storage>,
+    policy: PersistPolicy,
+    encode: fn(&A::RootModel) -> Result<Value, PersistError>,
+    decode: fn(&mut A::RootModel, Value) -> Result<(), PersistError>,
+    pub(crate) migrations: Migrations,
+    dirty: bool,
+    debounce: Option<AbortHandle>,
+    closed: bool,
+}
+
+impl<A: Application> Persister<A>
+where
+    A::RootModel: Snapshot,
+    <A::RootModel as Snapshot>::Snapshot: Serialize + DeserializeOwned,
+{
+    pub(crate) fn new(storage: impl Storage, policy: PersistPolicy) -> Self {
+        Self {
+            storage: Box::new(storage),
+            policy,
+            encode: |model| Ok(serde_json::to_value(model.snapshot())?),
+            decode: |model, value| {
+                model.restore(serde_json::from_value(value)?);
+                Ok(())
+            },
+            migrations: Migrations::new(),
+            dirty: false,
+            debounce: None,
+            closed: false,
+        }
+    }
+}
+
+impl<A: Application> Persister<A> {
+    /// Restores `model` from the stored snapshot, migrating it first if it is outdated. `model`
+    /// is left untouched if anything fails.
+    pub(crate) fn restore(&self, model: &mut A::RootModel) -> Result<(), PersistError> {
+        let Some(data) = self.storage.load()? else {
+            return Ok(());
+        };
+        let (version, state) = unwrap(serde_json::from_slice(&data)?);
+        let state = self.migrations.migrate(state, version)?;
+        (self.decode)(model, state)
+    }
+
+    /// Marks the model as changed. Returns a timer the host should wait on before saving if the
+    /// policy debounces saves; it resolves to `false` if it was superseded by a later change.
+    pub(crate) fn touch(&mut self) -> Option<impl Future<Output = bool> + MaybeSend + 'static> {
+        self.dirty = true;
+        let PersistPolicy::Debounce(delay) = &self.policy else {
+            return None;
+        };
+        if self.closed {
+            return None;
+        }
+        if let Some(previous) = self.debounce.take() {
+            previous.abort();
+        }
+        let (abort, registration) = AbortHandle::new_pair();
+        self.debounce = Some(abort);
+        let timer = Abortable::new(delay(), registration);
+        Some(async move { timer.await.is_ok() })
+    }
+
+    pub(crate) fn after_flush(&mut self, model: &ModelBase<A::RootModel>) {
+        if let PersistPolicy::EveryUpdate = self.policy {
+            self.save(model);
+        }
+    }
+
+    // stops debouncing so the host doesn't wait on timers while shutting down
+    pub(crate) fn close(&mut self) {
+        self.closed = true;
+        if let Some(debounce) = self.debounce.take() {
+            debounce.abort();
+        }
+    }
+
+    // saves even though no update marked the model as changed, e.g. after a reset
+    pub(crate) fn save_now(&mut self, model: &ModelBase<A::RootModel>) {
+        if let Some(debounce) = self.debounce.take() {
+            debounce.abort();
+        }
+        self.dirty = true;
+        self.save(model);
+    }
+
+    pub(crate) fn save(&mut self, model: &ModelBase<A::RootModel>) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let result = (self.encode)(&model.read())
+            .and_then(|state| Ok(serde_json::to_vec(&wrap(self.migrations.latest(), state))?))
+            .and_then(|data| self.storage.store(&data));
+        if let Err(error) = result {
+            tracing::error!(%error, "failed to save the root model");
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use core::fmt;
+use futures::channel::oneshot;
+
+/// The sending half of a reply, carried inside a message so whoever sent the message can wait
+/// for the outcome of the update.
+///
+/// Cloning a reply gives a detached one, so a cloned message never answers the original sender.
+pub struct Reply<T>(Option<oneshot::Sender<T>>);
+
+pub type ReplyReceiver<T> = oneshot::Receiver<T>;
+
+impl<T> Reply<T> {
+    pub fn channel() -> (Self, ReplyReceiver<T>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Some(tx)), rx)
+    }
+
+    /// A reply nobody is waiting on.
+    pub fn detached() -> Self {
+        Self(None)
+    }
+
+    /// Sends `value` back to the sender of the message, if it is still waiting.
+    pub fn send(self, value: T) {
+        if let Some(tx) = self.0 {
+            tx.send(value).ok();
+        }
+    }
+}
+
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self::detached()
+    }
+}
+
+impl<T> Default for Reply<T> {
+    fn default() -> Self {
+        Self::detached()
+    }
+}
+
+impl<T> fmt::Debug for Reply<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Reply")
+            .field(&if self.0.is_some() { "waiting" } else { "detached" })
+            .finish()
+    }
+}
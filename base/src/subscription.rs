@@ -0,0 +1,104 @@
+use crate::maybe::{MaybeLocalBoxStream, MaybeSend};
+use crate::{Key, Model};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use futures::{Stream, StreamExt};
+
+type StreamFn<M> =
+    Box<dyn_Maybe!(Send FnOnce() -> MaybeLocalBoxStream<'static, <M as Model>::Message>)>;
+
+/// A long-lived source of messages, identified by its key. The stream is only created when the
+/// subscription first appears in [`Model::subscriptions`], and dropped once it disappears.
+pub struct Subscription<M: Model> {
+    key: Key,
+    stream_fn: StreamFn<M>,
+}
+
+impl<M: Model> Subscription<M> {
+    pub fn new<F, S>(key: impl Into<Key>, stream_fn: F) -> Self
+    where
+        F: FnOnce() -> S + MaybeSend + 'static,
+        S: Stream<Item = M::Message> + MaybeSend + 'static,
+    {
+        Self {
+            key: key.into(),
+            stream_fn: Box::new(move || crate::maybe::boxed_stream(stream_fn())),
+        }
+    }
+
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn map<Parent>(self, lens: fn(M::Message) -> Parent::Message) -> Subscription<Parent>
+    where
+        Parent: Model<ForApp = M::ForApp>,
+    {
+        let stream_fn = self.stream_fn;
+        Subscription {
+            key: self.key,
+            stream_fn: Box::new(move || crate::maybe::boxed_stream(stream_fn().map(lens))),
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (Key, MaybeLocalBoxStream<'static, M::Message>) {
+        (self.key, (self.stream_fn)())
+    }
+}
+
+pub struct Subscriptions<M: Model>(Vec<Subscription<M>>);
+
+impl<M: Model> Subscriptions<M> {
+    pub fn none() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn with<F, S>(mut self, key: impl Into<Key>, stream_fn: F) -> Self
+    where
+        F: FnOnce() -> S + MaybeSend + 'static,
+        S: Stream<Item = M::Message> + MaybeSend + 'static,
+    {
+        self.push(Subscription::new(key, stream_fn));
+        self
+    }
+
+    pub fn push(&mut self, subscription: Subscription<M>) {
+        self.0.push(subscription);
+    }
+
+    /// Lifts the subscriptions of a child model into its parent, so they can be combined with
+    /// the parent's own subscriptions.
+    pub fn map<Parent>(self, lens: fn(M::Message) -> Parent::Message) -> Subscriptions<Parent>
+    where
+        Parent: Model<ForApp = M::ForApp>,
+    {
+        self.0.into_iter().map(|s| s.map(lens)).collect()
+    }
+}
+
+impl<M: Model> Default for Subscriptions<M> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<M: Model> FromIterator<Subscription<M>> for Subscriptions<M> {
+    fn from_iter<I: IntoIterator<Item = Subscription<M>>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<M: Model> Extend<Subscription<M>> for Subscriptions<M> {
+    fn extend<I: IntoIterator<Item = Subscription<M>>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl<M: Model> IntoIterator for Subscriptions<M> {
+    type Item = Subscription<M>;
+    type IntoIter = alloc::vec::IntoIter<Subscription<M>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<AckModel>;
+
+pub struct AckModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl AckModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn explode(&mut self) {
+        panic!("exploded");
+    }
+}
+
+#[test]
+fn waiting_resolves_once_the_update_is_flushed() {
+    let log = log();
+    let mut host = Host::<App>::new(AckModel {
+        entries: log.clone(),
+    });
+    let mut updater = AckUpdater::new(host.updater());
+    block_on(async {
+        let (pushed, _) = join!(updater.push_and_wait("a"), host.run_until_idle());
+        pushed.unwrap();
+    });
+    assert_eq!(entries(&log), ["a"]);
+}
+
+#[test]
+fn waiting_on_a_panicking_update_fails() {
+    let log = log();
+    let mut host = Host::<App>::builder()
+        .model(AckModel {
+            entries: log.clone(),
+        })
+        .on_panic(PanicPolicy::Skip)
+        .build();
+    let mut exploding = AckUpdater::new(host.updater());
+    let mut pushing = exploding.clone();
+    block_on(async {
+        let (exploded, pushed, _) = join!(
+            exploding.explode_and_wait(),
+            pushing.push_and_wait("after"),
+            host.run_until_idle(),
+        );
+        assert!(matches!(exploded, Err(Error::MessageDropped)));
+        pushed.unwrap();
+    });
+    assert_eq!(entries(&log), ["after"]);
+}
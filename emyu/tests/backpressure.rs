@@ -0,0 +1,88 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+
+type App = AdHocApp<BackpressureModel>;
+
+pub struct BackpressureModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl BackpressureModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+}
+
+fn host(backpressure: Backpressure) -> (Host<App>, BackpressureUpdater, Log) {
+    let log = log();
+    let host = Host::<App>::builder()
+        .model(BackpressureModel {
+            entries: log.clone(),
+        })
+        .buffer_size(2)
+        .backpressure(backpressure)
+        .build();
+    let updater = BackpressureUpdater::new(host.updater());
+    (host, updater, log)
+}
+
+#[test]
+fn drop_newest_keeps_the_messages_sent_first() {
+    let (mut host, mut updater, log) = host(Backpressure::DropNewest);
+    for entry in ["a", "b", "c", "d"] {
+        updater.try_push(entry).unwrap();
+    }
+    assert_eq!(host.dropped_messages(), 2);
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["a", "b"]);
+}
+
+#[test]
+fn drop_oldest_keeps_the_messages_sent_last() {
+    let (mut host, mut updater, log) = host(Backpressure::DropOldest);
+    for entry in ["a", "b", "c", "d"] {
+        updater.try_push(entry).unwrap();
+    }
+    assert_eq!(host.dropped_messages(), 2);
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["c", "d"]);
+}
+
+#[test]
+fn block_rejects_messages_once_the_lane_is_full() {
+    let (mut host, mut updater, log) = host(Backpressure::Block);
+    let mut sent = 0;
+    let error = loop {
+        match updater.try_push("a") {
+            Ok(()) => sent += 1,
+            Err(error) => break error,
+        }
+        assert!(sent <= 8, "the channel never filled up");
+    };
+    assert!(matches!(error, Error::HostChannelFull));
+    assert!(sent >= 2);
+    assert_eq!(host.dropped_messages(), 0);
+
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log).len(), sent);
+    updater.try_push("b").unwrap();
+}
+
+#[test]
+fn lanes_fill_up_separately() {
+    let (mut host, mut updater, log) = host(Backpressure::DropNewest);
+    let mut low = BackpressureUpdater::new(host.updater().with_priority(Priority::Low));
+    low.try_push("low 1").unwrap();
+    low.try_push("low 2").unwrap();
+    updater.try_push("normal 1").unwrap();
+    updater.try_push("normal 2").unwrap();
+    assert_eq!(host.dropped_messages(), 0);
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["normal 1", "normal 2", "low 1", "low 2"]);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type App = AdHocApp<BurstModel>;
+
+pub struct BurstModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl BurstModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+}
+
+/// Counts the flushes of the host.
+#[derive(Clone, Default)]
+struct Flushes(Arc<AtomicUsize>);
+
+impl Flushes {
+    fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Middleware<App> for Flushes {
+    fn after_flush(&mut self, _model: ModelBaseReader<BurstModel>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Builds a host that went through its initial flush, with `batching` if given.
+fn host(log: &Log, flushes: &Flushes, batching: Option<usize>) -> Host<App> {
+    let mut builder = Host::<App>::builder()
+        .model(BurstModel {
+            entries: log.clone(),
+        })
+        .middleware(flushes.clone());
+    if let Some(max_batch_size) = batching {
+        builder = builder.batching(max_batch_size);
+    }
+    let mut host = builder.build();
+    block_on(host.run_until_idle());
+    host
+}
+
+const BURST: [&str; 5] = ["a", "b", "c", "d", "e"];
+
+async fn send_burst(updater: &mut BurstUpdater) {
+    for entry in BURST {
+        updater.push(entry).await;
+    }
+}
+
+#[test]
+fn each_message_is_flushed_without_batching() {
+    let log = log();
+    let flushes = Flushes::default();
+    let mut host = host(&log, &flushes, None);
+    let initial = flushes.count();
+    let mut updater = BurstUpdater::new(host.updater());
+    block_on(async {
+        send_burst(&mut updater).await;
+        assert_eq!(host.step().await, 1);
+        assert_eq!(host.run_until_idle().await, 4);
+    });
+    assert_eq!(entries(&log), BURST);
+    assert_eq!(flushes.count() - initial, 5);
+}
+
+#[test]
+fn burst_is_flushed_once() {
+    let log = log();
+    let flushes = Flushes::default();
+    let mut host = host(&log, &flushes, Some(10));
+    let initial = flushes.count();
+    let mut updater = BurstUpdater::new(host.updater());
+    block_on(async {
+        send_burst(&mut updater).await;
+        assert_eq!(host.step().await, 5);
+        assert_eq!(host.step().await, 0);
+    });
+    assert_eq!(entries(&log), BURST);
+    assert_eq!(flushes.count() - initial, 1);
+}
+
+#[test]
+fn batches_stop_at_max_batch_size() {
+    let log = log();
+    let flushes = Flushes::default();
+    let mut host = host(&log, &flushes, Some(2));
+    let initial = flushes.count();
+    let mut updater = BurstUpdater::new(host.updater());
+    block_on(async {
+        send_burst(&mut updater).await;
+        assert_eq!(host.step().await, 2);
+        assert_eq!(entries(&log), ["a", "b"]);
+        assert_eq!(host.step().await, 2);
+        assert_eq!(host.step().await, 1);
+    });
+    assert_eq!(entries(&log), BURST);
+    assert_eq!(flushes.count() - initial, 3);
+}
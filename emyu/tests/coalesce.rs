@@ -0,0 +1,116 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<CoalesceModel>;
+
+pub struct CoalesceModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl CoalesceModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    #[emyu(coalesce)]
+    pub fn position(&mut self, position: &'static str) {
+        self.entries
+            .writer()
+            .update(|entries| entries.push(position));
+    }
+}
+
+fn model(log: &Log) -> CoalesceModel {
+    CoalesceModel {
+        entries: log.clone(),
+    }
+}
+
+#[test]
+fn newer_message_replaces_the_pending_one_in_place() {
+    let log = log();
+    let mut host = Host::<App>::new(model(&log));
+    let mut updater = CoalesceUpdater::new(host.updater());
+    updater.try_position("1").unwrap();
+    updater.try_push("x").unwrap();
+    updater.try_position("2").unwrap();
+    updater.try_position("3").unwrap();
+    assert_eq!(block_on(host.run_until_idle()), 2);
+    assert_eq!(entries(&log), ["3", "x"]);
+}
+
+#[test]
+fn only_messages_of_the_same_lane_are_coalesced() {
+    let log = log();
+    let mut host = Host::<App>::new(model(&log));
+    let mut updater = CoalesceUpdater::new(host.updater());
+    let mut high = CoalesceUpdater::new(host.updater().with_priority(Priority::High));
+    updater.try_position("1").unwrap();
+    high.try_position("2").unwrap();
+    updater.try_position("3").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["2", "3"]);
+}
+
+#[test]
+fn waiting_on_a_replaced_message_resolves_with_its_replacement() {
+    let log = log();
+    let mut host = Host::<App>::new(model(&log));
+    let mut first = CoalesceUpdater::new(host.updater());
+    let mut second = first.clone();
+    block_on(async {
+        let (first, second, _) = join!(
+            first.position_and_wait("1"),
+            second.position_and_wait("2"),
+            host.run_until_idle(),
+        );
+        first.unwrap();
+        second.unwrap();
+    });
+    assert_eq!(entries(&log), ["2"]);
+}
+
+// keeps sending faster than the host processes, and returns how many messages were still queued
+// at the end
+fn backlog(backpressure: Backpressure) -> usize {
+    let mut host = Host::<App>::builder()
+        .model(model(&log()))
+        .buffer_size(2)
+        .backpressure(backpressure)
+        .build();
+    let mut updater = CoalesceUpdater::new(host.updater());
+    block_on(async {
+        for _ in 0..20 {
+            for _ in 0..3 {
+                updater.try_push("a").ok();
+            }
+            updater.try_position("1").ok();
+            host.step().await;
+        }
+        host.run_until_idle().await
+    })
+}
+
+// the host keeps its own queue of messages taken out of the channel to coalesce them, which must
+// not grow past the buffer size either
+#[test]
+fn backpressure_still_bounds_the_queue() {
+    for backpressure in [
+        Backpressure::DropNewest,
+        Backpressure::DropOldest,
+        Backpressure::Block,
+    ] {
+        let backlog = backlog(backpressure);
+        assert!(
+            backlog <= 6,
+            "{backpressure:?} left {backlog} messages queued"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use emyu::{KeyValueStore, PersistError, Storage};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Keeps the snapshot of the root model in memory, shared between clones.
+#[derive(Clone, Default)]
+pub struct MemoryStorage(Arc<Mutex<Option<Vec<u8>>>>);
+
+impl MemoryStorage {
+    /// The stored snapshot, as saved by the host.
+    pub fn snapshot(&self) -> Option<Value> {
+        let data = self.0.lock().unwrap();
+        Some(serde_json::from_slice(data.as_ref()?).unwrap())
+    }
+
+    /// Stores `snapshot` as is, as if an earlier run had saved it.
+    pub fn set_snapshot(&self, snapshot: Value) {
+        *self.0.lock().unwrap() = Some(serde_json::to_vec(&snapshot).unwrap());
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<Option<Vec<u8>>, PersistError> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn store(&mut self, data: &[u8]) -> Result<(), PersistError> {
+        *self.0.lock().unwrap() = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+/// Keeps persisted fields in memory, shared between clones.
+#[derive(Clone, Default)]
+pub struct MemoryStore(Arc<Mutex<HashMap<String, Value>>>);
+
+impl MemoryStore {
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn load(&self, key: &str) -> Result<Option<Value>, PersistError> {
+        Ok(self.get(key))
+    }
+
+    fn store(&self, key: &str, value: Value) -> Result<(), PersistError> {
+        self.0.lock().unwrap().insert(key.to_string(), value);
+        Ok(())
+    }
+}
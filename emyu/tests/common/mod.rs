@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+use emyu::Signal;
+use std::task::Poll;
+
+#[cfg(feature = "persistence")]
+mod memory;
+
+#[cfg(feature = "persistence")]
+#[allow(unused_imports)]
+pub use memory::*;
+
+/// The signal test models log their entries to.
+pub type Log = Signal<Vec<&'static str>>;
+
+pub fn log() -> Log {
+    Signal::new(Vec::new())
+}
+
+pub fn entries(log: &Log) -> Vec<&'static str> {
+    log.reader().read().clone()
+}
+
+/// Waits a turn, so that the futures joined with this one make progress.
+pub async fn yield_now() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
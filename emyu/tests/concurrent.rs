@@ -0,0 +1,150 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::{Either, select};
+use futures::join;
+
+type App = AdHocApp<ConcurrentModel>;
+
+pub struct ConcurrentModel {
+    count: Signal<u32>,
+    report: Option<oneshot::Sender<u32>>,
+    entries: Log,
+}
+
+/// Reports the count it sees after waiting a turn.
+#[derive(Debug)]
+struct Record {
+    report: Option<oneshot::Sender<u32>>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Record {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        yield_now().await;
+        let count = *ctx.read().count.reader().read();
+        if let Some(report) = self.report.take() {
+            report.send(count).ok();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Open(Option<oneshot::Sender<()>>);
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Open {
+    type ForApp = App;
+
+    async fn apply(&mut self, _ctx: &mut CommandContext<'_, App>) {
+        if let Some(open) = self.0.take() {
+            open.send(()).ok();
+        }
+    }
+}
+
+/// Logs "opened" once `gate` is opened.
+#[derive(Debug)]
+struct Wait(oneshot::Receiver<()>);
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Wait {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        if (&mut self.0).await.is_ok() {
+            ctx.send_message(ConcurrentMessage::Push { entry: "opened" })
+                .await;
+        }
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl ConcurrentModel {
+    pub fn new();
+
+    #[emyu(init)]
+    fn init(&mut self, ctx: &mut UpdateContext<App>) {
+        if let Some(report) = self.report.take() {
+            ctx.emit_command(Record {
+                report: Some(report),
+            });
+        }
+    }
+
+    pub fn add(&mut self, n: u32) {
+        self.count.writer().update(|count| *count += n);
+    }
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    /// Waits in a sequential command on a keyed command emitted before it.
+    pub fn hand_off(&mut self, ctx: &mut UpdateContext<App>) {
+        let (open, gate) = oneshot::channel();
+        ctx.emit_keyed_command("open", CommandPolicy::Switch, Open(Some(open)));
+        ctx.emit_command(Wait(gate));
+    }
+}
+
+fn model(report: Option<oneshot::Sender<u32>>, log: &Log) -> ConcurrentModel {
+    ConcurrentModel {
+        count: Signal::new(0),
+        report,
+        entries: log.clone(),
+    }
+}
+
+#[test]
+fn commands_progress_while_messages_keep_coming() {
+    let (report, seen) = oneshot::channel();
+    let host = Host::<App>::builder()
+        .model(model(Some(report), &log()))
+        .command_execution(CommandExecution::Concurrent)
+        .source(futures::stream::iter(0..1000), |_| ConcurrentMessage::Add {
+            n: 1,
+        })
+        .build();
+    let shutdown = host.shutdown_handle();
+    let (_, seen) = block_on(async {
+        join!(host.run(), async {
+            let seen = seen.await.unwrap();
+            shutdown.shutdown().await;
+            seen
+        })
+    });
+    assert!(
+        seen < 1000,
+        "the command only ran after all {seen} messages"
+    );
+}
+
+#[test]
+fn keyed_commands_progress_while_a_sequential_command_waits() {
+    let log = log();
+    let mut host = Host::<App>::new(model(None, &log));
+    let mut updater = ConcurrentUpdater::new(host.updater());
+    updater.try_hand_off().unwrap();
+    block_on(async {
+        let idle = Box::pin(host.run_until_idle());
+        let stuck = Box::pin(async {
+            for _ in 0..100 {
+                yield_now().await;
+            }
+        });
+        assert!(
+            matches!(select(idle, stuck).await, Either::Left(_)),
+            "the sequential command is still waiting"
+        );
+    });
+    assert_eq!(entries(&log), ["opened"]);
+}
@@ -0,0 +1,106 @@
+use emyu::*;
+use futures::StreamExt;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<AccountModel>;
+
+pub struct AccountModel {
+    balance: Signal<u32>,
+    last_error: Signal<Option<String>>,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl AccountModel {
+    pub fn new();
+
+    #[emyu(errors(signal = "last_error", host))]
+    pub fn withdraw(&mut self, amount: u32) -> Result<u32, String> {
+        let balance = *self.balance.reader().read();
+        if amount > balance {
+            return Err(format!("cannot withdraw {amount} out of {balance}"));
+        }
+        self.balance.writer().set(balance - amount);
+        Ok(balance - amount)
+    }
+
+    pub fn last_error(&self) -> Signal<Option<String>>;
+}
+
+fn host() -> Host<App> {
+    Host::<App>::new(AccountModel {
+        balance: Signal::new(10),
+        last_error: Signal::new(None),
+    })
+}
+
+fn failures(events: HostEvents) -> Vec<(String, String)> {
+    block_on(events.collect::<Vec<_>>())
+        .into_iter()
+        .filter_map(|event| match event {
+            HostEvent::UpdateFailed {
+                message_debug,
+                error,
+            } => Some((message_debug, error)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn error_is_returned_to_the_caller_and_set_on_the_signal() {
+    let mut host = host();
+    let mut updater = AccountUpdater::new(host.updater());
+    let mut getter = AccountGetter::new(host.getter());
+    block_on(async {
+        let (withdrawn, _) = join!(updater.withdraw(20), host.run_until_idle());
+        assert_eq!(
+            withdrawn.unwrap(),
+            Err("cannot withdraw 20 out of 10".into())
+        );
+    });
+    assert_eq!(
+        *getter.last_error().reader().read(),
+        Some("cannot withdraw 20 out of 10".into())
+    );
+}
+
+#[test]
+fn success_clears_the_signal() {
+    let mut host = host();
+    let mut updater = AccountUpdater::new(host.updater());
+    let mut getter = AccountGetter::new(host.getter());
+    block_on(async {
+        let (failed, _) = join!(updater.withdraw(20), host.run_until_idle());
+        assert!(failed.unwrap().is_err());
+    });
+    assert!(getter.last_error().reader().read().is_some());
+
+    block_on(async {
+        let (succeeded, _) = join!(updater.withdraw(5), host.run_until_idle());
+        assert_eq!(succeeded.unwrap(), Ok(5));
+    });
+    assert_eq!(*getter.last_error().reader().read(), None);
+}
+
+#[test]
+fn errors_are_reported_as_host_events() {
+    let mut host = host();
+    let events = host.events();
+    let mut updater = AccountUpdater::new(host.updater());
+    let mut first = updater.clone();
+    block_on(async {
+        let (succeeded, failed, _) = join!(
+            first.withdraw(5),
+            updater.withdraw(20),
+            host.run_until_idle(),
+        );
+        assert!(succeeded.unwrap().is_ok());
+        assert!(failed.unwrap().is_err());
+    });
+    drop(host);
+    let failures = failures(events);
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].0.contains("Withdraw"));
+    assert_eq!(failures[0].1, "cannot withdraw 20 out of 5");
+}
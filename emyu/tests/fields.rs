@@ -0,0 +1,94 @@
+#![cfg(feature = "persistence")]
+
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+use serde_json::json;
+
+type App = AdHocApp<SettingsModel>;
+
+pub struct SettingsModel {
+    theme: Signal<String>,
+    volume: Signal<u32>,
+    draft: Signal<String>,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl SettingsModel {
+    pub fn new();
+
+    pub fn set_theme(&mut self, theme: String) {
+        self.theme.writer().set(theme);
+    }
+
+    pub fn set_draft(&mut self, draft: String) {
+        self.draft.writer().set(draft);
+    }
+
+    #[emyu(persist = "theme")]
+    pub fn theme(&self) -> Signal<String>;
+
+    #[emyu(persist = "volume")]
+    pub fn volume(&self) -> Signal<u32>;
+
+    pub fn draft(&self) -> Signal<String>;
+}
+
+fn start(store: &MemoryStore) -> Host<App> {
+    Host::<App>::builder()
+        .model(SettingsModel {
+            theme: Signal::new("light".into()),
+            volume: Signal::new(50),
+            draft: Signal::new(String::new()),
+        })
+        .field_store(store.clone())
+        .build()
+}
+
+#[test]
+fn fields_are_loaded_when_the_host_is_built() {
+    let store = MemoryStore::default();
+    store.store("theme", json!("dark")).unwrap();
+    let host = start(&store);
+    let mut getter = SettingsGetter::new(host.getter());
+    assert_eq!(*getter.theme().reader().read(), "dark");
+    assert_eq!(*getter.volume().reader().read(), 50);
+}
+
+#[test]
+fn unreadable_values_leave_the_field_as_is() {
+    let store = MemoryStore::default();
+    store.store("volume", json!("loud")).unwrap();
+    let host = start(&store);
+    let mut getter = SettingsGetter::new(host.getter());
+    assert_eq!(*getter.volume().reader().read(), 50);
+}
+
+#[test]
+fn only_changed_fields_are_written_back() {
+    let store = MemoryStore::default();
+    let mut host = start(&store);
+    let mut updater = SettingsUpdater::new(host.updater());
+    updater.try_set_theme("dark".into()).unwrap();
+    updater.try_set_draft("hello".into()).unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(store.get("theme"), Some(json!("dark")));
+    assert_eq!(store.get("volume"), None);
+    assert_eq!(store.get("draft"), None);
+}
+
+#[test]
+fn written_fields_are_loaded_on_the_next_start() {
+    let store = MemoryStore::default();
+    let mut host = start(&store);
+    let mut updater = SettingsUpdater::new(host.updater());
+    updater.try_set_theme("dark".into()).unwrap();
+    block_on(host.run_until_idle());
+    drop(host);
+
+    let host = start(&store);
+    let mut getter = SettingsGetter::new(host.getter());
+    assert_eq!(*getter.theme().reader().read(), "dark");
+}
@@ -0,0 +1,201 @@
+use emyu::*;
+use futures::executor::block_on;
+
+fn flags(history: &History<u32>) -> (bool, bool) {
+    (
+        *history.can_undo().reader().read(),
+        *history.can_redo().reader().read(),
+    )
+}
+
+#[test]
+fn undo_and_redo_walk_the_recorded_steps() {
+    let mut history = History::new(10);
+    history.record(1);
+    history.record(2);
+    assert_eq!(history.undo(3), Some(2));
+    assert_eq!(history.undo(2), Some(1));
+    assert_eq!(history.undo(1), None);
+    assert_eq!(history.redo(1), Some(2));
+    assert_eq!(history.redo(2), Some(3));
+    assert_eq!(history.redo(3), None);
+}
+
+#[test]
+fn recording_forgets_what_could_be_redone() {
+    let mut history = History::new(10);
+    history.record(1);
+    assert_eq!(history.undo(2), Some(1));
+    history.record(1);
+    assert_eq!(history.redo(3), None);
+}
+
+#[test]
+fn depth_limit_forgets_the_oldest_steps() {
+    let mut history = History::new(2);
+    for snapshot in 1..=3 {
+        history.record(snapshot);
+    }
+    assert_eq!(history.undo(4), Some(3));
+    assert_eq!(history.undo(3), Some(2));
+    assert_eq!(history.undo(2), None);
+}
+
+#[test]
+fn zero_depth_records_nothing() {
+    let mut history = History::new(0);
+    history.record(1);
+    assert_eq!(history.undo(2), None);
+    assert_eq!(flags(&history), (false, false));
+}
+
+#[test]
+fn grouped_records_make_a_single_step() {
+    let mut history = History::new(10);
+    for snapshot in 1..=3 {
+        history.record_grouped("typing", snapshot);
+    }
+    assert_eq!(history.undo(4), Some(1));
+    assert_eq!(history.undo(1), None);
+}
+
+#[test]
+fn groups_end_on_another_group_a_plain_record_or_close_group() {
+    let mut history = History::new(10);
+    history.record_grouped("typing", 1);
+    history.record_grouped("dragging", 2);
+    history.record(3);
+    history.record_grouped("typing", 4);
+    history.close_group();
+    history.record_grouped("typing", 5);
+    for expected in (1..=5).rev() {
+        assert_eq!(history.undo(expected + 1), Some(expected));
+    }
+}
+
+#[test]
+fn undoing_ends_the_group() {
+    let mut history = History::new(10);
+    history.record_grouped("typing", 1);
+    history.record_grouped("typing", 2);
+    assert_eq!(history.undo(3), Some(1));
+    history.record_grouped("typing", 1);
+    history.record_grouped("typing", 2);
+    assert_eq!(history.undo(3), Some(1));
+    assert_eq!(history.undo(1), None);
+}
+
+#[test]
+fn signals_follow_what_can_be_undone_and_redone() {
+    let mut history = History::new(10);
+    assert_eq!(flags(&history), (false, false));
+    history.record(1);
+    assert_eq!(flags(&history), (true, false));
+    history.undo(2);
+    assert_eq!(flags(&history), (false, true));
+    history.redo(1);
+    assert_eq!(flags(&history), (true, false));
+    history.undo(2);
+    history.record(1);
+    assert_eq!(flags(&history), (true, false));
+    history.clear();
+    assert_eq!(flags(&history), (false, false));
+}
+
+mod editor {
+    use super::*;
+
+    type App = AdHocApp<EditorModel>;
+
+    pub struct TextModel {
+        text: Signal<String>,
+    }
+
+    impl Snapshot for TextModel {
+        type Snapshot = String;
+
+        fn snapshot(&self) -> String {
+            self.text.reader().read().clone()
+        }
+
+        fn restore(&mut self, snapshot: String) {
+            self.text.writer().set(snapshot);
+        }
+    }
+
+    #[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+    pub impl TextModel {
+        pub fn new();
+
+        pub fn text(&self) -> Signal<String>;
+    }
+
+    pub struct EditorModel {
+        document: ModelBase<TextModel>,
+        history: History<String>,
+        can_undo: Signal<bool>,
+        can_redo: Signal<bool>,
+    }
+
+    #[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+    pub impl EditorModel {
+        pub fn new();
+
+        pub fn type_text(&mut self, text: &'static str) {
+            self.history.record_model_grouped("typing", &self.document);
+            let document = self.document.read();
+            document
+                .text
+                .writer()
+                .update(|current| current.push_str(text));
+        }
+
+        pub fn undo(&mut self) {
+            self.history.undo_model(&self.document);
+        }
+
+        pub fn redo(&mut self) {
+            self.history.redo_model(&self.document);
+        }
+
+        pub fn can_undo(&self) -> Signal<bool>;
+
+        pub fn can_redo(&self) -> Signal<bool>;
+    }
+
+    fn editor() -> EditorModel {
+        let history = History::new(10);
+        EditorModel {
+            document: ModelBase::new(TextModel {
+                text: Signal::new(String::new()),
+            }),
+            can_undo: history.can_undo(),
+            can_redo: history.can_redo(),
+            history,
+        }
+    }
+
+    #[test]
+    fn zoomed_child_is_undone_and_redone() {
+        let mut host = Host::<App>::new(editor());
+        let mut updater = EditorUpdater::new(host.updater());
+        let mut getter = EditorGetter::new(host.getter());
+        let mut text = TextGetter::new(host.getter().zoom(|editor| &editor.document));
+        updater.try_type_text("a").unwrap();
+        updater.try_type_text("b").unwrap();
+        block_on(host.run_until_idle());
+        assert_eq!(*text.text().reader().read(), "ab");
+        assert!(*getter.can_undo().reader().read());
+
+        updater.try_undo().unwrap();
+        block_on(host.run_until_idle());
+        assert_eq!(*text.text().reader().read(), "");
+        assert!(!*getter.can_undo().reader().read());
+        assert!(*getter.can_redo().reader().read());
+
+        updater.try_redo().unwrap();
+        block_on(host.run_until_idle());
+        assert_eq!(*text.text().reader().read(), "ab");
+        assert!(!*getter.can_redo().reader().read());
+    }
+}
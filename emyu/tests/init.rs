@@ -0,0 +1,110 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::StreamExt;
+use futures::executor::block_on;
+
+type App = AdHocApp<InitModel>;
+
+pub struct InitModel {
+    entries: Log,
+    broken: bool,
+}
+
+/// Pushes "loaded", like a command loading the initial data.
+#[derive(Debug)]
+struct Load;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Load {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        ctx.send_message(InitMessage::Push { entry: "loaded" })
+            .await;
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl InitModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    #[emyu(init)]
+    fn init(&mut self, ctx: &mut UpdateContext<App>) {
+        if self.broken {
+            panic!("init exploded");
+        }
+        self.entries.writer().update(|entries| entries.push("init"));
+        ctx.emit_command(Load);
+    }
+}
+
+fn host(log: &Log, broken: bool) -> (Host<App>, InitUpdater) {
+    let host = Host::<App>::new(InitModel {
+        entries: log.clone(),
+        broken,
+    });
+    let updater = InitUpdater::new(host.updater());
+    (host, updater)
+}
+
+#[test]
+fn init_runs_once_before_the_first_message() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, false);
+    block_on(async {
+        updater.push("first").await;
+        host.run_until_idle().await;
+        // like after an update, the message sent by the init command comes first
+        assert_eq!(entries(&log), ["init", "loaded", "first"]);
+
+        updater.push("second").await;
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["init", "loaded", "first", "second"]);
+}
+
+#[test]
+fn init_runs_when_dispatching_first() {
+    let log = log();
+    let (mut host, _) = host(&log, false);
+    block_on(async {
+        host.dispatch(InitMessage::Push { entry: "first" }).await;
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["init", "first", "loaded"]);
+}
+
+#[test]
+fn panic_in_init_is_caught() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, true);
+    let mut events = host.events();
+    block_on(async {
+        updater.push("first").await;
+        host.run_until_idle().await;
+    });
+    drop(host);
+
+    // the init commands never run, the messages still do
+    assert_eq!(entries(&log), ["first"]);
+    let events = block_on(events.by_ref().collect::<Vec<_>>());
+    match &events[..] {
+        [
+            HostEvent::Panicked {
+                message_debug,
+                payload,
+            },
+        ] => {
+            assert!(message_debug.ends_with("InitModel::init"));
+            assert_eq!(payload, "init exploded");
+        }
+        events => panic!("unexpected events {events:?}"),
+    }
+}
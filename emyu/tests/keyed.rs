@@ -0,0 +1,132 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+
+type App = AdHocApp<KeyedModel>;
+
+pub struct KeyedModel {
+    entries: Log,
+}
+
+/// Logs `name` once `gate` is opened or dropped.
+#[derive(Debug)]
+struct Gated {
+    name: &'static str,
+    gate: oneshot::Receiver<()>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Gated {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        (&mut self.gate).await.ok();
+        ctx.send_message(KeyedMessage::Push { entry: self.name })
+            .await;
+    }
+}
+
+#[derive(Default)]
+struct Hits(u32);
+
+/// Counts in the [`Hits`] state how many times it ran.
+#[derive(Debug)]
+struct Hit;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Hit {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        ctx.state_mut::<Hits>().0 += 1;
+        ctx.send_message(KeyedMessage::Push { entry: "hit" }).await;
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl KeyedModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn keyed(
+        &mut self,
+        ctx: &mut UpdateContext<App>,
+        name: &'static str,
+        policy: CommandPolicy,
+        gate: oneshot::Receiver<()>,
+    ) {
+        ctx.emit_keyed_command("gated", policy, Gated { name, gate });
+    }
+
+    pub fn hit(&mut self, ctx: &mut UpdateContext<App>) {
+        ctx.emit_command(Hit);
+    }
+}
+
+#[test]
+fn sequential_commands_mutate_states_while_keyed_command_is_in_flight() {
+    let log = log();
+    let mut host = Host::<App>::builder()
+        .model(KeyedModel {
+            entries: log.clone(),
+        })
+        .state::<Hits>()
+        .build();
+    let mut updater = KeyedUpdater::new(host.updater());
+    let (open, gate) = oneshot::channel();
+    block_on(async {
+        updater.keyed("slow", CommandPolicy::Switch, gate).await;
+        updater.hit().await;
+        while host.step().await > 0 {}
+        assert_eq!(entries(&log), ["hit"]);
+
+        open.send(()).ok();
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["hit", "slow"]);
+}
+
+// emits two commands under the same key, opens both gates once both were handled, and returns
+// what ran
+fn run_two(policy: CommandPolicy) -> Vec<&'static str> {
+    let log = log();
+    let mut host = Host::<App>::new(KeyedModel {
+        entries: log.clone(),
+    });
+    let mut updater = KeyedUpdater::new(host.updater());
+    let (open_first, first) = oneshot::channel();
+    let (open_second, second) = oneshot::channel();
+    block_on(async {
+        updater.keyed("first", policy, first).await;
+        updater.keyed("second", policy, second).await;
+        while host.step().await > 0 {}
+        // the second command must not start before the first one finished when queued
+        open_second.send(()).ok();
+        open_first.send(()).ok();
+        host.run_until_idle().await;
+    });
+    entries(&log)
+}
+
+#[test]
+fn switch_cancels_the_command_in_flight() {
+    assert_eq!(run_two(CommandPolicy::Switch), ["second"]);
+}
+
+#[test]
+fn exhaust_drops_the_new_command() {
+    assert_eq!(run_two(CommandPolicy::Exhaust), ["first"]);
+}
+
+#[test]
+fn queue_runs_commands_one_after_another() {
+    assert_eq!(run_two(CommandPolicy::Queue), ["first", "second"]);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+
+type App = AdHocApp<LifecycleModel>;
+
+pub struct LifecycleModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl LifecycleModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+}
+
+fn builder(log: &Log) -> HostBuilder<App> {
+    Host::<App>::builder()
+        .model(LifecycleModel {
+            entries: log.clone(),
+        })
+        .suspend_when_paused(true)
+        .source(futures::stream::iter(["a", "b"]), |entry| {
+            LifecycleMessage::Push { entry }
+        })
+}
+
+#[test]
+fn suspended_host_leaves_sources_alone_until_resumed() {
+    let log = log();
+    let mut host = builder(&log).build();
+    let mut updater = LifecycleUpdater::new(host.updater());
+    let mut high = LifecycleUpdater::new(host.updater().with_priority(Priority::High));
+    host.set_lifecycle(Lifecycle::Paused);
+    updater.try_push("normal").unwrap();
+    high.try_push("high").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["high"]);
+
+    host.set_lifecycle(Lifecycle::Resumed);
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["high", "normal", "a", "b"]);
+}
+
+#[test]
+fn running_host_leaves_sources_alone_while_suspended() {
+    let log = log();
+    let host = builder(&log).build();
+    let lifecycle = host.lifecycle_handle();
+    let shutdown = host.shutdown_handle();
+    let mut high = LifecycleUpdater::new(host.updater().with_priority(Priority::High));
+    lifecycle.set(Lifecycle::Paused);
+    block_on(async {
+        futures::join!(host.run(), async {
+            high.push_and_wait("high").await.unwrap();
+            assert_eq!(entries(&log), ["high"]);
+            shutdown.shutdown().await;
+        })
+    });
+}
@@ -0,0 +1,260 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::join;
+use std::sync::{Arc, Mutex};
+
+type App = AdHocApp<LogModel>;
+
+pub struct LogModel {
+    entries: Log,
+}
+
+/// Pushes `entry` from a command.
+#[derive(Debug)]
+struct Echo {
+    entry: &'static str,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Echo {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        ctx.send_message(LogMessage::Push { entry: self.entry })
+            .await;
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl LogModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn echo(&mut self, ctx: &mut UpdateContext<App>, entry: &'static str) {
+        ctx.emit_command(Echo { entry });
+    }
+}
+
+type Trace = Arc<Mutex<Vec<String>>>;
+
+fn lines(trace: &Trace) -> Vec<String> {
+    trace.lock().unwrap().clone()
+}
+
+/// The lines of `trace`, without the flushes.
+fn updates(trace: &Trace) -> Vec<String> {
+    lines(trace)
+        .into_iter()
+        .filter(|line| !line.ends_with("flush"))
+        .collect()
+}
+
+/// Records every hook it goes through in `trace`.
+struct Tracer {
+    name: &'static str,
+    trace: Trace,
+}
+
+impl Middleware<App> for Tracer {
+    fn before_update(
+        &mut self,
+        _model: ModelBaseReader<LogModel>,
+        message: LogMessage,
+    ) -> Flow<App> {
+        let line = match &message {
+            LogMessage::Push { entry } => format!("{} before push {entry}", self.name),
+            LogMessage::Echo { entry } => format!("{} before echo {entry}", self.name),
+        };
+        self.trace.lock().unwrap().push(line);
+        Flow::Continue(message)
+    }
+
+    fn after_update(&mut self, _model: ModelBaseReader<LogModel>, commands: &CommandQueue<App>) {
+        let line = format!(
+            "{} after update with {} commands",
+            self.name,
+            commands.len()
+        );
+        self.trace.lock().unwrap().push(line);
+    }
+
+    fn after_flush(&mut self, _model: ModelBaseReader<LogModel>) {
+        let line = format!("{} after flush", self.name);
+        self.trace.lock().unwrap().push(line);
+    }
+}
+
+/// Drops "spam" and fixes "tpyo".
+struct Filter;
+
+impl Middleware<App> for Filter {
+    fn before_update(
+        &mut self,
+        _model: ModelBaseReader<LogModel>,
+        message: LogMessage,
+    ) -> Flow<App> {
+        match message {
+            LogMessage::Push { entry: "spam" } => Flow::Drop,
+            LogMessage::Push { entry: "tpyo" } => {
+                Flow::Continue(LogMessage::Push { entry: "typo" })
+            }
+            message => Flow::Continue(message),
+        }
+    }
+}
+
+/// Holds "slow" back until the gate is opened.
+struct Hold {
+    gate: Option<oneshot::Receiver<()>>,
+}
+
+impl Middleware<App> for Hold {
+    fn before_update(
+        &mut self,
+        _model: ModelBaseReader<LogModel>,
+        message: LogMessage,
+    ) -> Flow<App> {
+        match (&message, self.gate.take()) {
+            (LogMessage::Push { entry: "slow" }, Some(gate)) => Flow::delay(message, async move {
+                gate.await.ok();
+            }),
+            (_, gate) => {
+                self.gate = gate;
+                Flow::Continue(message)
+            }
+        }
+    }
+}
+
+fn tracer(name: &'static str, trace: &Trace) -> Tracer {
+    Tracer {
+        name,
+        trace: trace.clone(),
+    }
+}
+
+#[test]
+fn dropped_messages_skip_the_rest_of_the_chain() {
+    let log = log();
+    let trace = Trace::default();
+    let mut host = Host::<App>::builder()
+        .model(LogModel {
+            entries: log.clone(),
+        })
+        .middleware(tracer("first", &trace))
+        .middleware(Filter)
+        .middleware(tracer("last", &trace))
+        .build();
+    let mut updater = LogUpdater::new(host.updater());
+    block_on(async {
+        let (dropped, _) = join!(updater.push_and_wait("spam"), host.run_until_idle());
+        assert!(matches!(dropped, Err(Error::MessageDropped)));
+    });
+    assert_eq!(entries(&log), Vec::<&str>::new());
+    assert_eq!(updates(&trace), ["first before push spam"]);
+}
+
+#[test]
+fn replaced_messages_reach_the_rest_of_the_chain_and_the_model() {
+    let log = log();
+    let trace = Trace::default();
+    let mut host = Host::<App>::builder()
+        .model(LogModel {
+            entries: log.clone(),
+        })
+        .middleware(Filter)
+        .middleware(tracer("last", &trace))
+        .build();
+    let mut updater = LogUpdater::new(host.updater());
+    block_on(async {
+        updater.push("tpyo").await;
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["typo"]);
+    assert_eq!(
+        updates(&trace),
+        ["last before push typo", "last after update with 0 commands"]
+    );
+}
+
+#[test]
+fn delayed_messages_resume_after_the_middleware_holding_them() {
+    let log = log();
+    let trace = Trace::default();
+    let (open, gate) = oneshot::channel();
+    let mut host = Host::<App>::builder()
+        .model(LogModel {
+            entries: log.clone(),
+        })
+        .middleware(tracer("first", &trace))
+        .middleware(Hold { gate: Some(gate) })
+        .middleware(tracer("last", &trace))
+        .build();
+    let mut updater = LogUpdater::new(host.updater());
+    block_on(async {
+        updater.push("slow").await;
+        updater.push("fast").await;
+        while host.step().await > 0 {}
+        assert_eq!(entries(&log), ["fast"]);
+
+        open.send(()).unwrap();
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["fast", "slow"]);
+    let before: Vec<_> = updates(&trace)
+        .into_iter()
+        .filter(|line| line.contains("before"))
+        .collect();
+    assert_eq!(
+        before,
+        [
+            "first before push slow",
+            "first before push fast",
+            "last before push fast",
+            "last before push slow",
+        ]
+    );
+}
+
+#[test]
+fn hooks_run_in_order() {
+    let trace = Trace::default();
+    let mut host = Host::<App>::builder()
+        .model(LogModel { entries: log() })
+        .middleware(tracer("first", &trace))
+        .middleware(tracer("last", &trace))
+        .build();
+    let mut updater = LogUpdater::new(host.updater());
+    block_on(async {
+        updater.echo("hi").await;
+        host.run_until_idle().await;
+    });
+    assert_eq!(
+        lines(&trace),
+        [
+            // the initial flush
+            "first after flush",
+            "last after flush",
+            "first before echo hi",
+            "last before echo hi",
+            "first after update with 1 commands",
+            "last after update with 1 commands",
+            "first after flush",
+            "last after flush",
+            "first before push hi",
+            "last before push hi",
+            "first after update with 0 commands",
+            "last after update with 0 commands",
+            "first after flush",
+            "last after flush",
+        ]
+    );
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "persistence")]
+
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::executor::block_on;
+use serde_json::{Value, json};
+
+type App = AdHocApp<VolumeModel>;
+
+pub struct VolumeModel {
+    volume: Signal<u32>,
+}
+
+impl Snapshot for VolumeModel {
+    type Snapshot = u32;
+
+    fn snapshot(&self) -> u32 {
+        *self.volume.reader().read()
+    }
+
+    fn restore(&mut self, snapshot: u32) {
+        self.volume.writer().set(snapshot);
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl VolumeModel {
+    pub fn new();
+
+    pub fn set_volume(&mut self, volume: u32) {
+        self.volume.writer().set(volume);
+    }
+
+    pub fn volume(&self) -> Signal<u32>;
+}
+
+// version 0 stored the volume as a string, version 1 as a number out of 10 and version 2 out of
+// 100
+fn migrations() -> Migrations {
+    Migrations::new()
+        .step(|value: Value| {
+            let volume = value.as_str().and_then(|volume| volume.parse::<u32>().ok());
+            match volume {
+                Some(volume) => Ok(json!(volume)),
+                None => Err(format!("{value} is not a volume")),
+            }
+        })
+        .step(|value: Value| Ok::<_, String>(json!(value.as_u64().unwrap() * 10)))
+}
+
+fn builder(storage: &MemoryStorage) -> HostBuilder<App> {
+    Host::<App>::builder()
+        .model(VolumeModel {
+            volume: Signal::new(50),
+        })
+        .persist(storage.clone(), PersistPolicy::EveryUpdate)
+        .migrations(migrations())
+}
+
+fn restored(snapshot: Value) -> Result<u32, PersistError> {
+    let storage = MemoryStorage::default();
+    storage.set_snapshot(snapshot);
+    let host = builder(&storage).try_build()?;
+    let mut getter = VolumeGetter::new(host.getter());
+    Ok(*getter.volume().reader().read())
+}
+
+#[test]
+fn snapshots_are_saved_at_the_latest_version() {
+    let storage = MemoryStorage::default();
+    let mut host = builder(&storage).build();
+    let mut updater = VolumeUpdater::new(host.updater());
+    updater.try_set_volume(70).unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(
+        storage.snapshot(),
+        Some(json!({ "__emyu_version": 2, "__emyu_state": 70 }))
+    );
+}
+
+#[test]
+fn unversioned_snapshot_goes_through_every_migration() {
+    assert_eq!(restored(json!("3")).unwrap(), 30);
+}
+
+#[test]
+fn unversioned_snapshot_with_envelope_like_fields_is_not_unwrapped() {
+    let error = restored(json!({ "version": 2, "state": 30 })).unwrap_err();
+    assert!(matches!(error, PersistError::Migration { from: 0, .. }));
+}
+
+#[test]
+fn older_snapshot_goes_through_the_remaining_migrations() {
+    let snapshot = json!({ "__emyu_version": 1, "__emyu_state": 3 });
+    assert_eq!(restored(snapshot).unwrap(), 30);
+}
+
+#[test]
+fn latest_snapshot_is_restored_as_is() {
+    let snapshot = json!({ "__emyu_version": 2, "__emyu_state": 30 });
+    assert_eq!(restored(snapshot).unwrap(), 30);
+}
+
+#[test]
+fn newer_snapshot_is_rejected() {
+    let snapshot = json!({ "__emyu_version": 3, "__emyu_state": 300 });
+    let error = restored(snapshot).unwrap_err();
+    assert!(matches!(
+        error,
+        PersistError::UnknownVersion {
+            found: 3,
+            latest: 2
+        }
+    ));
+}
+
+#[test]
+fn failing_migration_is_reported() {
+    let error = restored(json!("loud")).unwrap_err();
+    assert!(matches!(error, PersistError::Migration { from: 0, .. }));
+}
+
+#[test]
+fn build_falls_back_to_the_given_model() {
+    for snapshot in [
+        json!("loud"),
+        json!({ "__emyu_version": 3, "__emyu_state": 300 }),
+    ] {
+        let storage = MemoryStorage::default();
+        storage.set_snapshot(snapshot);
+        let host = builder(&storage).build();
+        let mut getter = VolumeGetter::new(host.getter());
+        assert_eq!(*getter.volume().reader().read(), 50);
+    }
+}
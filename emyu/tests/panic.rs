@@ -0,0 +1,162 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::StreamExt;
+use futures::executor::block_on;
+
+type App = AdHocApp<PanicModel>;
+
+pub struct PanicModel {
+    entries: Log,
+}
+
+impl Snapshot for PanicModel {
+    type Snapshot = Vec<&'static str>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        entries(&self.entries)
+    }
+
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        self.entries.writer().set(snapshot);
+    }
+}
+
+#[derive(Debug)]
+struct Explode;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Explode {
+    type ForApp = App;
+
+    async fn apply(&mut self, _ctx: &mut CommandContext<'_, App>) {
+        panic!("command exploded");
+    }
+}
+
+struct Ran;
+
+/// Adds [`Ran`] to the world.
+#[derive(Debug)]
+struct Run;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Run {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        ctx.insert_state(Ran);
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl PanicModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    /// Pushes `entry`, then panics.
+    pub fn explode(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+        panic!("update exploded");
+    }
+
+    /// Emits a panicking command followed by [`Run`].
+    pub fn explode_command(&mut self, ctx: &mut UpdateContext<App>) {
+        ctx.emit_command(Explode);
+        ctx.emit_command(Run);
+    }
+}
+
+fn host(log: &Log, policy: PanicPolicy<App>) -> (Host<App>, PanicUpdater) {
+    let host = Host::<App>::builder()
+        .model(PanicModel {
+            entries: log.clone(),
+        })
+        .on_panic(policy)
+        .build();
+    let updater = PanicUpdater::new(host.updater());
+    (host, updater)
+}
+
+#[test]
+fn skip_keeps_the_changes_and_carries_on() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, PanicPolicy::Skip);
+    updater.try_push("a").unwrap();
+    updater.try_explode("b").unwrap();
+    updater.try_push("c").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["a", "b", "c"]);
+}
+
+#[test]
+fn restart_puts_back_the_model_of_the_last_good_update() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, PanicPolicy::restart());
+    updater.try_push("a").unwrap();
+    updater.try_explode("b").unwrap();
+    updater.try_push("c").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["a", "c"]);
+}
+
+#[test]
+fn stop_drops_the_queued_messages() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, PanicPolicy::Stop);
+    updater.try_push("a").unwrap();
+    updater.try_explode("b").unwrap();
+    updater.try_push("c").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["a", "b"]);
+    assert_eq!(block_on(host.step()), 0);
+}
+
+#[test]
+fn stop_drops_the_remaining_commands() {
+    let (mut host, mut updater) = host(&log(), PanicPolicy::Stop);
+    updater.try_explode_command().unwrap();
+    block_on(host.run_until_idle());
+    assert!(!host.world().contains::<Ran>());
+}
+
+#[test]
+fn stopped_host_leaves_run() {
+    let log = log();
+    let (host, mut updater) = host(&log, PanicPolicy::Stop);
+    updater.try_explode_command().unwrap();
+    updater.try_push("late").unwrap();
+    block_on(host.run());
+    assert!(entries(&log).is_empty());
+}
+
+#[test]
+fn panics_are_reported_as_events() {
+    let log = log();
+    let (mut host, mut updater) = host(&log, PanicPolicy::Skip);
+    let mut events = host.events();
+    updater.try_explode("a").unwrap();
+    updater.try_explode_command().unwrap();
+    block_on(host.run_until_idle());
+    drop(host);
+
+    let events = block_on(events.by_ref().collect::<Vec<_>>());
+    let payloads: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            HostEvent::Panicked { payload, .. } => Some(payload.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(payloads, ["update exploded", "command exploded"]);
+    match &events[0] {
+        HostEvent::Panicked { message_debug, .. } => assert!(message_debug.contains("Explode")),
+        event => panic!("unexpected event {event:?}"),
+    }
+}
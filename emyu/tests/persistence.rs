@@ -0,0 +1,143 @@
+#![cfg(feature = "persistence")]
+
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::FutureExt;
+use serde_json::json;
+use std::path::PathBuf;
+
+type App = AdHocApp<CounterModel>;
+
+pub struct CounterModel {
+    count: Signal<u32>,
+}
+
+impl Snapshot for CounterModel {
+    type Snapshot = u32;
+
+    fn snapshot(&self) -> u32 {
+        *self.count.reader().read()
+    }
+
+    fn restore(&mut self, snapshot: u32) {
+        self.count.writer().set(snapshot);
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl CounterModel {
+    pub fn new();
+
+    pub fn increment(&mut self) {
+        self.count.writer().update(|count| *count += 1);
+    }
+
+    pub fn count(&self) -> Signal<u32>;
+}
+
+fn counter() -> CounterModel {
+    CounterModel {
+        count: Signal::new(0),
+    }
+}
+
+fn saved(count: u32) -> Option<serde_json::Value> {
+    Some(json!({ "__emyu_version": 0, "__emyu_state": count }))
+}
+
+/// A snapshot file removed again once the test is done.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("emyu-{}-{name}.json", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+#[test]
+fn model_is_restored_before_the_first_message() {
+    let file = TempFile::new("restore");
+    for expected in [1, 2] {
+        let mut host = Host::<App>::builder()
+            .model(counter())
+            .persist(FileStorage::new(&file.0), PersistPolicy::EveryUpdate)
+            .build();
+        let mut updater = CounterUpdater::new(host.updater());
+        let mut getter = CounterGetter::new(host.getter());
+        updater.try_increment().unwrap();
+        block_on(host.run_until_idle());
+        assert_eq!(*getter.count().reader().read(), expected);
+    }
+}
+
+#[test]
+fn every_update_saves_right_after_the_update() {
+    let storage = MemoryStorage::default();
+    let mut host = Host::<App>::builder()
+        .model(counter())
+        .persist(storage.clone(), PersistPolicy::EveryUpdate)
+        .build();
+    let mut updater = CounterUpdater::new(host.updater());
+    for expected in [1, 2] {
+        updater.try_increment().unwrap();
+        block_on(host.step());
+        assert_eq!(storage.snapshot(), saved(expected));
+    }
+}
+
+#[test]
+fn debounce_saves_once_updates_settle() {
+    let storage = MemoryStorage::default();
+    let (settle, settled) = oneshot::channel::<()>();
+    let settled = settled.shared();
+    let mut host = Host::<App>::builder()
+        .model(counter())
+        .persist(
+            storage.clone(),
+            PersistPolicy::debounce(move || settled.clone().map(|_| ())),
+        )
+        .build();
+    let mut updater = CounterUpdater::new(host.updater());
+    for _ in 0..2 {
+        updater.try_increment().unwrap();
+        block_on(host.step());
+        assert_eq!(storage.snapshot(), None);
+    }
+
+    settle.send(()).unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(storage.snapshot(), saved(2));
+}
+
+#[test]
+fn pausing_saves_right_away() {
+    let file = TempFile::new("paused");
+    let mut host = Host::<App>::builder()
+        .model(counter())
+        .persist(
+            FileStorage::new(&file.0),
+            PersistPolicy::debounce(futures::future::pending),
+        )
+        .build();
+    let mut updater = CounterUpdater::new(host.updater());
+    updater.try_increment().unwrap();
+    block_on(host.step());
+    assert!(!file.0.exists());
+
+    host.set_lifecycle(Lifecycle::Paused);
+    let data = std::fs::read(&file.0).unwrap();
+    let snapshot: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(Some(snapshot), saved(1));
+}
@@ -0,0 +1,109 @@
+use emyu::*;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<ItemsModel>;
+
+pub struct ItemsModel {
+    items: Signal<Vec<&'static str>>,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl ItemsModel {
+    pub fn new();
+
+    /// Adds `item` and returns its index.
+    pub fn add(&mut self, item: &'static str) -> usize {
+        self.items.writer().update(|items| {
+            items.push(item);
+            items.len() - 1
+        })
+    }
+
+    pub fn add_broken(&mut self, _item: &'static str) -> usize {
+        panic!("broken");
+    }
+
+    pub fn items(&self) -> Signal<Vec<&'static str>>;
+}
+
+fn host() -> Host<App> {
+    Host::<App>::new(ItemsModel {
+        items: Signal::new(Vec::new()),
+    })
+}
+
+#[test]
+fn sending_returns_the_value() {
+    let mut host = host();
+    let mut updater = ItemsUpdater::new(host.updater());
+    let mut second = updater.clone();
+    block_on(async {
+        let (first, second, _) = join!(updater.add("a"), second.add("b"), host.run_until_idle());
+        assert_eq!(first.unwrap(), 0);
+        assert_eq!(second.unwrap(), 1);
+    });
+}
+
+#[test]
+fn try_sending_hands_back_the_receiver() {
+    let mut host = host();
+    let mut updater = ItemsUpdater::new(host.updater());
+    let mut reply = updater.try_add("a").unwrap();
+    assert_eq!(reply.try_recv(), Ok(None));
+    block_on(host.run_until_idle());
+    assert_eq!(block_on(reply), Ok(0));
+}
+
+#[test]
+fn waiting_returns_the_value_once_flushed() {
+    let mut host = host();
+    let mut updater = ItemsUpdater::new(host.updater());
+    let mut getter = ItemsGetter::new(host.getter());
+    let items = getter.items();
+    block_on(async {
+        join!(
+            async {
+                let index = updater.add_and_wait("a").await.unwrap();
+                assert_eq!(items.reader().read()[index], "a");
+            },
+            host.run_until_idle(),
+        )
+    });
+}
+
+#[test]
+fn dropped_reply_fails_the_caller() {
+    let mut host = host();
+    let mut updater = ItemsUpdater::new(host.updater());
+    let mut waiting = updater.clone();
+    let mut receiving = updater.clone();
+    let reply = receiving.try_add_broken("a").unwrap();
+    block_on(async {
+        let (sent, waited, _) = join!(
+            updater.add_broken("b"),
+            waiting.add_broken_and_wait("c"),
+            host.run_until_idle(),
+        );
+        assert!(matches!(sent, Err(Error::ReplyDropped)));
+        // the message is dropped before its reply
+        assert!(matches!(waited, Err(Error::MessageDropped)));
+    });
+    assert!(block_on(reply).is_err());
+}
+
+#[cfg(feature = "thread-safe")]
+#[test]
+fn blocking_returns_the_value() {
+    let host = host();
+    let mut updater = ItemsUpdater::new(host.updater());
+    let shutdown = host.shutdown_handle();
+    let running = std::thread::spawn(move || block_on(host.run()));
+    assert_eq!(updater.add_blocking("a").unwrap(), 0);
+    assert!(matches!(
+        updater.add_broken_blocking("b"),
+        Err(Error::ReplyDropped)
+    ));
+    block_on(shutdown.shutdown());
+    running.join().unwrap();
+}
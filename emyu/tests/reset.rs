@@ -0,0 +1,287 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<ResetModel>;
+
+pub struct ResetModel {
+    entries: Log,
+}
+
+#[derive(Default)]
+struct Settings;
+
+#[derive(Debug)]
+struct Token;
+
+/// Adds a [`Token`] to the world once `gate` is opened, then never finishes.
+#[derive(Debug)]
+struct Install {
+    gate: Option<oneshot::Receiver<()>>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Install {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        if let Some(gate) = self.gate.take() {
+            gate.await.ok();
+        }
+        ctx.insert_state(Token);
+        futures::future::pending::<()>().await;
+    }
+}
+
+/// Logs whether the world has a [`Token`] and [`Settings`].
+#[derive(Debug)]
+struct Inspect;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Inspect {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        let token = match ctx.contains_state::<Token>() {
+            true => "token",
+            false => "no token",
+        };
+        let settings = match ctx.contains_state::<Settings>() {
+            true => "settings",
+            false => "no settings",
+        };
+        ctx.send_message(ResetMessage::Push { entry: token }).await;
+        ctx.send_message(ResetMessage::Push { entry: settings })
+            .await;
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl ResetModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn install(&mut self, ctx: &mut UpdateContext<App>, gate: oneshot::Receiver<()>) {
+        ctx.emit_command(Install { gate: Some(gate) });
+    }
+
+    pub fn inspect(&mut self, ctx: &mut UpdateContext<App>) {
+        ctx.emit_command(Inspect);
+    }
+
+    pub fn entries(&self) -> Signal<Vec<&'static str>>;
+}
+
+fn model() -> ResetModel {
+    ResetModel { entries: log() }
+}
+
+#[test]
+fn reset_fails_without_a_model_factory() {
+    let mut host = Host::<App>::new(model());
+    assert!(matches!(block_on(host.reset()), Err(Error::NoModelFactory)));
+}
+
+#[test]
+fn reset_starts_over_with_a_new_model_and_world() {
+    let host = Host::<App>::builder()
+        .model_factory(model)
+        .state::<Settings>()
+        .command_execution(CommandExecution::Concurrent)
+        .build();
+    let mut updater = ResetUpdater::new(host.updater());
+    let mut getter = ResetGetter::new(host.getter());
+    let reset = host.reset_handle();
+    let shutdown = host.shutdown_handle();
+    let (open, gate) = oneshot::channel();
+    block_on(async {
+        join!(host.run(), async {
+            updater.push_and_wait("before").await.unwrap();
+            updater.install_and_wait(gate).await.unwrap();
+            // lets the command add its token without the host seeing it yet
+            open.send(()).unwrap();
+            yield_now().await;
+
+            reset.reset().await.unwrap();
+            updater.inspect_and_wait().await.unwrap();
+            updater.push_and_wait("after").await.unwrap();
+            shutdown.shutdown().await;
+        })
+    });
+    assert_eq!(
+        entries(&getter.entries()),
+        ["no token", "settings", "after"]
+    );
+}
+
+#[test]
+fn reset_drops_states_added_without_a_factory() {
+    let host = Host::<App>::builder()
+        .model_factory(model)
+        .state_with(Settings)
+        .build();
+    let mut updater = ResetUpdater::new(host.updater());
+    let mut getter = ResetGetter::new(host.getter());
+    let reset = host.reset_handle();
+    let shutdown = host.shutdown_handle();
+    block_on(async {
+        join!(host.run(), async {
+            reset.reset().await.unwrap();
+            updater.inspect_and_wait().await.unwrap();
+            shutdown.shutdown().await;
+        })
+    });
+    assert_eq!(entries(&getter.entries()), ["no token", "no settings"]);
+}
+
+#[test]
+fn resetting_a_stopped_host_fails() {
+    let host = Host::<App>::builder().model_factory(model).build();
+    let reset = host.reset_handle();
+    drop(host);
+    assert!(matches!(
+        block_on(reset.reset()),
+        Err(Error::HostChannelClosed)
+    ));
+}
+
+mod zoomed {
+    use emyu::*;
+    use futures::executor::block_on;
+
+    type App = AdHocApp<ParentModel>;
+
+    pub struct ChildModel {
+        value: Signal<u32>,
+    }
+
+    #[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+    pub impl ChildModel {
+        pub fn new();
+
+        pub fn value(&self) -> Signal<u32>;
+    }
+
+    pub struct ParentModel {
+        child: ModelBase<ChildModel>,
+    }
+
+    #[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+    pub impl ParentModel {
+        pub fn new();
+
+        pub fn noop(&mut self) {}
+    }
+
+    fn parent(value: u32) -> ParentModel {
+        ParentModel {
+            child: ModelBase::new(ChildModel {
+                value: Signal::new(value),
+            }),
+        }
+    }
+
+    #[test]
+    fn zoomed_getters_follow_a_reset() {
+        let mut host = Host::<App>::builder()
+            .model(parent(1))
+            .model_factory(|| parent(2))
+            .build();
+        let mut getter = ChildGetter::new(host.getter().zoom(|parent| &parent.child));
+        assert_eq!(*getter.value().reader().read(), 1);
+        block_on(host.reset()).unwrap();
+        assert_eq!(*getter.value().reader().read(), 2);
+    }
+}
+
+#[cfg(feature = "persistence")]
+mod persisted {
+    use super::common::*;
+    use emyu::*;
+    use futures::executor::block_on;
+
+    type App = AdHocApp<ProfileModel>;
+
+    pub struct ProfileModel {
+        name: Signal<String>,
+        theme: Signal<String>,
+    }
+
+    impl Snapshot for ProfileModel {
+        type Snapshot = String;
+
+        fn snapshot(&self) -> String {
+            self.name.reader().read().clone()
+        }
+
+        fn restore(&mut self, snapshot: String) {
+            self.name.writer().set(snapshot);
+        }
+    }
+
+    #[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+    pub impl ProfileModel {
+        pub fn new();
+
+        pub fn log_in(&mut self, name: String) {
+            self.name.writer().set(name);
+        }
+
+        pub fn set_theme(&mut self, theme: String) {
+            self.theme.writer().set(theme);
+        }
+
+        pub fn name(&self) -> Signal<String>;
+
+        #[emyu(persist = "theme")]
+        pub fn theme(&self) -> Signal<String>;
+    }
+
+    fn guest() -> ProfileModel {
+        ProfileModel {
+            name: Signal::new("guest".into()),
+            theme: Signal::new("light".into()),
+        }
+    }
+
+    #[test]
+    fn reset_saves_the_new_model_and_reloads_persisted_fields() {
+        let storage = MemoryStorage::default();
+        let store = MemoryStore::default();
+        let mut host = Host::<App>::builder()
+            .model_factory(guest)
+            .persist(storage.clone(), PersistPolicy::EveryUpdate)
+            .field_store(store.clone())
+            .build();
+        let mut updater = ProfileUpdater::new(host.updater());
+        let mut getter = ProfileGetter::new(host.getter());
+        updater.try_log_in("alice".into()).unwrap();
+        updater.try_set_theme("dark".into()).unwrap();
+        block_on(host.run_until_idle());
+        assert_eq!(storage.snapshot().unwrap()["__emyu_state"], "alice");
+
+        block_on(host.reset()).unwrap();
+        assert_eq!(storage.snapshot().unwrap()["__emyu_state"], "guest");
+        assert_eq!(*getter.name().reader().read(), "guest");
+        assert_eq!(*getter.theme().reader().read(), "dark");
+
+        // the next start doesn't bring the old user back either
+        let host = Host::<App>::builder()
+            .model(guest())
+            .persist(storage, PersistPolicy::EveryUpdate)
+            .field_store(store)
+            .build();
+        let mut getter = ProfileGetter::new(host.getter());
+        assert_eq!(*getter.name().reader().read(), "guest");
+        assert_eq!(*getter.theme().reader().read(), "dark");
+    }
+}
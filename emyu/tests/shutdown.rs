@@ -0,0 +1,170 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<ShutdownModel>;
+
+pub struct ShutdownModel {
+    entries: Log,
+}
+
+/// Logs `name` once `gate` is opened or dropped.
+#[derive(Debug)]
+struct Gated {
+    name: &'static str,
+    gate: oneshot::Receiver<()>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Gated {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        (&mut self.gate).await.ok();
+        ctx.send_message(ShutdownMessage::Push { entry: self.name })
+            .await;
+    }
+}
+
+/// Reports back through `done` once `gate` is opened.
+#[derive(Debug)]
+struct Relay {
+    gate: Option<oneshot::Receiver<()>>,
+    done: Option<oneshot::Sender<()>>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Relay {
+    type ForApp = App;
+
+    async fn apply(&mut self, _ctx: &mut CommandContext<'_, App>) {
+        if let Some(gate) = self.gate.take() {
+            gate.await.ok();
+        }
+        if let Some(done) = self.done.take() {
+            done.send(()).ok();
+        }
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl ShutdownModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn gated(
+        &mut self,
+        ctx: &mut UpdateContext<App>,
+        name: &'static str,
+        gate: oneshot::Receiver<()>,
+    ) {
+        ctx.emit_command(Gated { name, gate });
+    }
+
+    pub fn relay(
+        &mut self,
+        ctx: &mut UpdateContext<App>,
+        gate: Option<oneshot::Receiver<()>>,
+        done: oneshot::Sender<()>,
+    ) {
+        let relay = Relay {
+            gate,
+            done: Some(done),
+        };
+        ctx.emit_keyed_command("relay", CommandPolicy::Queue, relay);
+    }
+}
+
+fn model(log: &Log) -> ShutdownModel {
+    ShutdownModel {
+        entries: log.clone(),
+    }
+}
+
+#[test]
+fn queued_messages_are_applied_before_stopping() {
+    let log = log();
+    let host = Host::<App>::new(model(&log));
+    let mut updater = ShutdownUpdater::new(host.updater());
+    let shutdown = host.shutdown_handle();
+    for entry in ["a", "b", "c"] {
+        updater.try_push(entry).unwrap();
+    }
+    block_on(async { join!(host.run(), shutdown.shutdown()) });
+    assert_eq!(entries(&log), ["a", "b", "c"]);
+}
+
+#[test]
+fn keyed_commands_in_flight_and_queued_are_waited_on() {
+    let log = log();
+    let host = Host::<App>::new(model(&log));
+    let mut updater = ShutdownUpdater::new(host.updater());
+    let shutdown = host.shutdown_handle();
+    let (open, gate) = oneshot::channel();
+    let (first_done, mut first) = oneshot::channel();
+    let (second_done, mut second) = oneshot::channel();
+    block_on(async {
+        join!(host.run(), async {
+            updater.relay(Some(gate), first_done).await;
+            updater.relay(None, second_done).await;
+            updater.push_and_wait("after").await.unwrap();
+            join!(shutdown.shutdown(), async {
+                yield_now().await;
+                open.send(()).unwrap();
+            });
+        })
+    });
+    assert_eq!(entries(&log), ["after"]);
+    assert_eq!(first.try_recv(), Ok(Some(())));
+    assert_eq!(second.try_recv(), Ok(Some(())));
+}
+
+#[test]
+fn commands_in_flight_are_dropped_past_the_deadline() {
+    let log = log();
+    let host = Host::<App>::builder()
+        .model(model(&log))
+        .command_execution(CommandExecution::Concurrent)
+        .shutdown_deadline(|| async {})
+        .build();
+    let mut updater = ShutdownUpdater::new(host.updater());
+    let shutdown = host.shutdown_handle();
+    let (open, gate) = oneshot::channel();
+    let (open_keyed, keyed_gate) = oneshot::channel();
+    let (done, mut keyed) = oneshot::channel();
+    let (queued_done, mut queued) = oneshot::channel();
+    block_on(async {
+        join!(host.run(), async {
+            updater.gated_and_wait("slow", gate).await.unwrap();
+            updater.relay(Some(keyed_gate), done).await;
+            updater.relay_and_wait(None, queued_done).await.unwrap();
+            shutdown.shutdown().await;
+        })
+    });
+    assert!(entries(&log).is_empty());
+    assert!(open.send(()).is_err());
+    assert!(open_keyed.send(()).is_err());
+    assert_eq!(keyed.try_recv(), Err(oneshot::Canceled));
+    assert_eq!(queued.try_recv(), Err(oneshot::Canceled));
+}
+
+#[test]
+fn senders_fail_once_the_host_has_stopped() {
+    let host = Host::<App>::new(model(&log()));
+    let mut updater = ShutdownUpdater::new(host.updater());
+    let shutdown = host.shutdown_handle();
+    block_on(async { join!(host.run(), shutdown.shutdown()) });
+    assert!(matches!(
+        updater.try_push("late"),
+        Err(Error::HostChannelClosed)
+    ));
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::join;
+
+type App = AdHocApp<SourceModel>;
+
+pub struct SourceModel {
+    entries: Log,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl SourceModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+}
+
+fn push(entry: &'static str) -> SourceMessage {
+    SourceMessage::Push { entry }
+}
+
+fn builder(log: &Log) -> HostBuilder<App> {
+    Host::<App>::builder().model(SourceModel {
+        entries: log.clone(),
+    })
+}
+
+#[test]
+fn keyed_source_replaces_the_one_with_the_same_key() {
+    let log = log();
+    let (old_tx, old_rx) = mpsc::unbounded();
+    let (new_tx, new_rx) = mpsc::unbounded();
+    let mut host = builder(&log)
+        .source_keyed("feed", old_rx, push)
+        .source_keyed("feed", new_rx, push)
+        .build();
+    block_on(host.run_until_idle());
+    assert!(old_tx.is_closed());
+
+    new_tx.unbounded_send("new").unwrap();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["new"]);
+    assert!(host.remove_source(&"feed".into()));
+}
+
+#[test]
+fn removed_source_is_dropped() {
+    let log = log();
+    let (tx, rx) = mpsc::unbounded();
+    let mut host = builder(&log).source_keyed("feed", rx, push).build();
+    tx.unbounded_send("kept").unwrap();
+    block_on(host.run_until_idle());
+
+    assert!(host.remove_source(&"feed".into()));
+    assert!(!host.remove_source(&"feed".into()));
+    // the stream is only dropped once the host polls the sources again
+    tx.unbounded_send("lost").unwrap();
+    block_on(host.run_until_idle());
+    assert!(tx.is_closed());
+    assert_eq!(entries(&log), ["kept"]);
+}
+
+#[test]
+fn source_handle_removes_the_source_while_running() {
+    let log = log();
+    let (tx, rx) = mpsc::unbounded();
+    let host = builder(&log).source_keyed("feed", rx, push).build();
+    let sources = host.source_handle();
+    let shutdown = host.shutdown_handle();
+    block_on(async {
+        join!(host.run(), async {
+            tx.unbounded_send("kept").unwrap();
+            while entries(&log).is_empty() {
+                yield_now().await;
+            }
+            sources.remove("feed");
+            while !tx.is_closed() {
+                yield_now().await;
+            }
+            shutdown.shutdown().await;
+        })
+    });
+    assert_eq!(entries(&log), ["kept"]);
+}
+
+#[test]
+fn ended_source_is_forgotten() {
+    let log = log();
+    let mut host = builder(&log)
+        .source_keyed("feed", futures::stream::iter(["a", "b"]), push)
+        .build();
+    block_on(host.run_until_idle());
+    assert_eq!(entries(&log), ["a", "b"]);
+    assert!(!host.remove_source(&"feed".into()));
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::StreamExt;
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use std::sync::{Arc, Mutex};
+
+type App = AdHocApp<FeedModel>;
+
+type Tap = (&'static str, mpsc::UnboundedSender<&'static str>);
+
+/// The channels behind the subscription streams, in the order the host started them.
+#[derive(Clone, Default)]
+struct Taps(Arc<Mutex<Vec<Tap>>>);
+
+impl Taps {
+    fn open(&self, feed: &'static str) -> mpsc::UnboundedReceiver<&'static str> {
+        let (tx, rx) = mpsc::unbounded();
+        self.0.lock().unwrap().push((feed, tx));
+        rx
+    }
+
+    fn started(&self) -> Vec<&'static str> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(feed, _)| *feed)
+            .collect()
+    }
+
+    /// The feeds whose stream is still held by the host.
+    fn running(&self) -> Vec<&'static str> {
+        let taps = self.0.lock().unwrap();
+        taps.iter()
+            .filter(|(_, tx)| !tx.is_closed())
+            .map(|(feed, _)| *feed)
+            .collect()
+    }
+
+    fn send(&self, feed: &'static str, entry: &'static str) {
+        let taps = self.0.lock().unwrap();
+        let (_, tx) = taps.iter().rfind(|(name, _)| *name == feed).unwrap();
+        tx.unbounded_send(entry).unwrap();
+    }
+}
+
+pub struct FeedModel {
+    entries: Log,
+    feeds: Vec<&'static str>,
+    taps: Taps,
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl FeedModel {
+    pub fn new();
+
+    pub fn push(&mut self, entry: &'static str) {
+        self.entries.writer().update(|entries| entries.push(entry));
+    }
+
+    pub fn follow(&mut self, feed: &'static str) {
+        self.feeds.push(feed);
+    }
+
+    pub fn unfollow(&mut self, feed: &'static str) {
+        self.feeds.retain(|followed| *followed != feed);
+    }
+
+    #[emyu(subscriptions)]
+    fn subscriptions(&self) -> Subscriptions<Self> {
+        self.feeds
+            .iter()
+            .map(|&feed| {
+                let taps = self.taps.clone();
+                Subscription::new(feed, move || {
+                    taps.open(feed).map(|entry| FeedMessage::Push { entry })
+                })
+            })
+            .collect()
+    }
+}
+
+fn model(log: &Log, taps: &Taps) -> FeedModel {
+    FeedModel {
+        entries: log.clone(),
+        feeds: Vec::new(),
+        taps: taps.clone(),
+    }
+}
+
+#[test]
+fn subscriptions_start_and_stop_with_their_key() {
+    let log = log();
+    let taps = Taps::default();
+    let mut host = Host::<App>::new(model(&log, &taps));
+    let mut updater = FeedUpdater::new(host.updater());
+    block_on(async {
+        updater.follow("news").await;
+        host.run_until_idle().await;
+        assert_eq!(taps.running(), ["news"]);
+
+        taps.send("news", "headline");
+        host.run_until_idle().await;
+        assert_eq!(entries(&log), ["headline"]);
+
+        // the news stream is kept as long as its key is
+        updater.follow("sports").await;
+        host.run_until_idle().await;
+        assert_eq!(taps.started(), ["news", "sports"]);
+        assert_eq!(taps.running(), ["news", "sports"]);
+
+        updater.unfollow("news").await;
+        host.run_until_idle().await;
+        assert_eq!(taps.running(), ["sports"]);
+
+        taps.send("sports", "score");
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["headline", "score"]);
+}
+
+#[test]
+fn suspended_host_stops_the_subscriptions_until_it_resumes() {
+    let log = log();
+    let taps = Taps::default();
+    let mut host = Host::<App>::builder()
+        .model(model(&log, &taps))
+        .suspend_when_paused(true)
+        .build();
+    let mut updater = FeedUpdater::new(host.updater());
+    block_on(async {
+        updater.follow("news").await;
+        host.run_until_idle().await;
+        assert_eq!(taps.running(), ["news"]);
+
+        host.set_lifecycle(Lifecycle::Paused);
+        host.run_until_idle().await;
+        assert_eq!(taps.running(), Vec::<&str>::new());
+
+        host.set_lifecycle(Lifecycle::Resumed);
+        host.run_until_idle().await;
+        assert_eq!(taps.started(), ["news", "news"]);
+        assert_eq!(taps.running(), ["news"]);
+
+        taps.send("news", "headline");
+        host.run_until_idle().await;
+    });
+    assert_eq!(entries(&log), ["headline"]);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::*;
+use emyu::*;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+
+type App = AdHocApp<WorldModel>;
+
+pub struct WorldModel;
+
+#[derive(Default)]
+struct Removals(u32);
+
+#[derive(Debug)]
+struct Token;
+
+/// Adds a [`Token`] to the world once `gate` is opened, then never finishes.
+#[derive(Debug)]
+struct Install {
+    gate: Option<oneshot::Receiver<()>>,
+}
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Install {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        if let Some(gate) = self.gate.take() {
+            gate.await.ok();
+        }
+        ctx.insert_state(Token);
+        futures::future::pending::<()>().await;
+    }
+}
+
+/// Removes the [`Token`] from the world and counts the removal in [`Removals`].
+#[derive(Debug)]
+struct Uninstall;
+
+#[cfg_attr(feature = "thread-safe", async_trait::async_trait)]
+#[cfg_attr(not(feature = "thread-safe"), async_trait::async_trait(?Send))]
+impl Command for Uninstall {
+    type ForApp = App;
+
+    async fn apply(&mut self, ctx: &mut CommandContext<'_, App>) {
+        if ctx.remove_state::<Token>() {
+            ctx.state_entry::<Removals>().or_default().0 += 1;
+        }
+    }
+}
+
+#[emyu_macros::model(for_app = "App", dispatcher(meta(base(derive(Clone)))))]
+pub impl WorldModel {
+    pub fn new();
+
+    pub fn install(&mut self, ctx: &mut UpdateContext<App>, gate: oneshot::Receiver<()>) {
+        ctx.emit_command(Install { gate: Some(gate) });
+    }
+
+    pub fn uninstall(&mut self, ctx: &mut UpdateContext<App>) {
+        ctx.emit_command(Uninstall);
+    }
+}
+
+#[test]
+fn commands_add_and_remove_states_at_runtime() {
+    let mut host = Host::<App>::new(WorldModel);
+    let mut updater = WorldUpdater::new(host.updater());
+
+    updater.try_uninstall().unwrap();
+    block_on(host.run_until_idle());
+    assert!(!host.world().contains::<Removals>());
+
+    host.world_mut().insert(Token);
+    updater.try_uninstall().unwrap();
+    block_on(host.run_until_idle());
+    assert!(!host.world().contains::<Token>());
+    assert_eq!(host.world().get::<Removals>().0, 1);
+}
+
+#[test]
+fn states_added_by_concurrent_commands_reach_the_host() {
+    let mut host = Host::<App>::builder()
+        .model(WorldModel)
+        .command_execution(CommandExecution::Concurrent)
+        .build();
+    let mut updater = WorldUpdater::new(host.updater());
+    let (open, gate) = oneshot::channel();
+    updater.try_install(gate).unwrap();
+    block_on(async {
+        host.step().await;
+        open.send(()).unwrap();
+        // the command never finishes, but adds the token as soon as it runs
+        let idle = Box::pin(host.run_until_idle());
+        futures::future::select(idle, Box::pin(yield_now())).await;
+    });
+    assert!(!host.world().contains::<Token>());
+    assert!(host.world_mut().contains::<Token>());
+}
@@ -271,10 +271,14 @@ impl<'a> ParsedField<'a> {
         match (self.args.kind(), self.ty.mutability.is_some()) {
             (FieldKind::Field, false) => quote! { let #name = &*#name; },
             (FieldKind::Field, true) => quote! { let mut #name = &mut *#name; },
-            (FieldKind::State, false) => quote! { let #name = #ctx_name.state::<#ty>(); },
-            (FieldKind::State, true) => {
-                quote! { let mut #name = #ctx_name.state_mut::<#ty>(); }
-            }
+            (FieldKind::State, false) => quote! {
+                let #name = #ctx_name.state::<#ty>();
+                let #name = &*#name;
+            },
+            (FieldKind::State, true) => quote! {
+                let mut #name = #ctx_name.state_mut::<#ty>();
+                let mut #name = &mut *#name;
+            },
         }
     }
 }
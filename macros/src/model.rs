@@ -8,7 +8,7 @@ use crate::utils::{InterfaceImpl, ThisCrate};
 use attr::ModelArgs;
 pub use attr::raw::ModelArgs as RawModelArgs;
 use proc_macro2::{Ident, TokenStream};
-use syn::{Attribute, Block, Type, TypePath, Visibility};
+use syn::{Attribute, Block, ReturnType, Type, TypePath, Visibility};
 
 struct ModelContext<'a> {
     crate_: ThisCrate,
@@ -18,16 +18,19 @@ struct ModelContext<'a> {
     new_fn: ParsedNewFn,
     updaters: Vec<ParsedUpdaterFn<'a>>,
     getters: Vec<ParsedGetterFn<'a>>,
+    subscriptions: Option<ParsedSubscriptionsFn<'a>>,
+    init: Option<ParsedInitFn<'a>>,
 }
 
 enum FnKind<'a> {
     // fn new();
     New(NewMethodArgs),
 
-    // fn updater(&mut self) {}
+    // fn updater(&mut self) [-> Ret] {}
     Updater {
         args: UpdaterGetterMethodArgs,
         ctx: Option<&'a Ident>,
+        output: &'a ReturnType,
         block: &'a Block,
     },
 
@@ -36,6 +39,18 @@ enum FnKind<'a> {
         args: UpdaterGetterMethodArgs,
         ty: &'a Type,
     },
+
+    // #[emyu(subscriptions)] fn subscriptions(&self) -> Subscriptions<Self> {}
+    Subscriptions {
+        ret_ty: &'a ReturnType,
+        block: &'a Block,
+    },
+
+    // #[emyu(init)] fn init(&mut self[, ctx: &mut UpdateContext<App>]) {}
+    Init {
+        ctx: Option<&'a Ident>,
+        block: &'a Block,
+    },
 }
 
 struct ParsedFnArg<'a> {
@@ -67,6 +82,7 @@ struct ParsedUpdaterFn<'a> {
     common: ParsedUpdaterGetterFn<'a>,
     fn_args: Vec<ParsedFnArg<'a>>,
     ctx: Option<&'a Ident>,
+    output: &'a ReturnType,
     block: &'a Block,
 }
 
@@ -75,6 +91,18 @@ struct ParsedGetterFn<'a> {
     ret_ty: &'a Type,
 }
 
+struct ParsedSubscriptionsFn<'a> {
+    fn_name: &'a Ident,
+    ret_ty: &'a ReturnType,
+    block: &'a Block,
+}
+
+struct ParsedInitFn<'a> {
+    fn_name: &'a Ident,
+    ctx: Option<&'a Ident>,
+    block: &'a Block,
+}
+
 pub fn build(item: InterfaceImpl, attrs: RawModelArgs) -> syn::Result<TokenStream> {
     Ok(ModelContext::parse(&item, attrs)?.generate())
 }
@@ -96,7 +96,9 @@ impl ModelArgs {
             .ok_or_else(|| syn::Error::new(span, "`dispatcher` is required"))?
             .into_config();
         let message = MessageEnumProperties::from_config(
-            raw.message,
+            raw.message
+                .map(raw::MessageDef::into_config)
+                .unwrap_or_default(),
             model_name,
             crate_,
             flutter_rust_bridge,
@@ -211,6 +213,22 @@ impl NewMethodArgs {
             return Err(invalid_position_error(span, "#[emyu(name(...))]"));
         };
 
+        if raw.persist.is_some() {
+            return Err(invalid_position_error(span, "#[emyu(persist = \"...\")]"));
+        }
+
+        if raw.errors.is_some() {
+            return Err(invalid_position_error(span, "#[emyu(errors(...))]"));
+        }
+
+        if raw.coalesce {
+            return Err(invalid_position_error(span, "#[emyu(coalesce)]"));
+        }
+
+        if raw.on_lifecycle {
+            return Err(invalid_position_error(span, "#[emyu(on_lifecycle)]"));
+        }
+
         Ok(())
     }
 
@@ -247,6 +265,10 @@ pub struct UpdaterGetterMethodArgs {
     pub message: MessageProperties,
     pub fn_name: Ident,
     pub fn_meta: Vec<ProcessedMeta>,
+    pub persist: Option<String>,
+    pub errors: Option<raw::ErrorsConfig>,
+    pub coalesce: bool,
+    pub on_lifecycle: bool,
 }
 
 impl UpdaterGetterMethodArgs {
@@ -272,6 +294,10 @@ impl UpdaterGetterMethodArgs {
                     W::fn_meta_owned(&raw.meta).collect()
                 }
             },
+            persist: raw.persist,
+            errors: raw.errors,
+            coalesce: raw.coalesce,
+            on_lifecycle: raw.on_lifecycle,
         }
     }
 
@@ -302,6 +328,10 @@ impl UpdaterGetterMethodArgs {
             return Err(invalid_position_error(span, "#[emyu(meta(getter(...)))]"));
         }
 
+        if raw.persist.is_some() {
+            return Err(invalid_position_error(span, "#[emyu(persist = \"...\")]"));
+        }
+
         Ok(())
     }
 
@@ -314,6 +344,18 @@ impl UpdaterGetterMethodArgs {
             return Err(invalid_position_error(span, "#[emyu(meta(updater(...)))]"));
         }
 
+        if raw.errors.is_some() {
+            return Err(invalid_position_error(span, "#[emyu(errors(...))]"));
+        }
+
+        if raw.coalesce {
+            return Err(invalid_position_error(span, "#[emyu(coalesce)]"));
+        }
+
+        if raw.on_lifecycle {
+            return Err(invalid_position_error(span, "#[emyu(on_lifecycle)]"));
+        }
+
         Ok(())
     }
 
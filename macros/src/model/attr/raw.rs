@@ -18,6 +18,15 @@ pub struct NameConfig {
     pub getter: Option<Ident>,
 }
 
+#[derive(FromMeta)]
+pub struct ErrorsConfig {
+    #[darling(default)]
+    pub signal: Option<Ident>,
+
+    #[darling(default)]
+    pub host: bool,
+}
+
 pub struct ProcessedMetaRef<'a>(&'a TokenStream);
 
 impl<'a> ProcessedMetaRef<'a> {
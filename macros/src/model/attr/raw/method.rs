@@ -1,4 +1,4 @@
-use crate::model::attr::raw::{MetaConfig, NameConfig};
+use crate::model::attr::raw::{ErrorsConfig, MetaConfig, NameConfig};
 use darling::FromAttributes;
 use proc_macro2::Ident;
 
@@ -13,4 +13,22 @@ pub struct MethodArgs {
 
     #[darling(default)]
     pub meta: Option<MetaConfig>,
+
+    #[darling(default)]
+    pub subscriptions: bool,
+
+    #[darling(default)]
+    pub persist: Option<String>,
+
+    #[darling(default)]
+    pub errors: Option<ErrorsConfig>,
+
+    #[darling(default)]
+    pub coalesce: bool,
+
+    #[darling(default)]
+    pub on_lifecycle: bool,
+
+    #[darling(default)]
+    pub init: bool,
 }
@@ -4,7 +4,7 @@ use darling::ast::NestedMeta;
 use proc_macro2::{Ident, Span};
 use syn::Meta;
 
-/// ```rust
+/// ```ignore
 /// #[emyu::model(
 ///     // Specify the application this model is for. Required.
 ///     for_app = "MyCoolApp",
@@ -102,16 +102,47 @@ use syn::Meta;
 ///     // - The header can only be the visibility followed by `fn`. No `async`, `const`, etc.
 ///     // - Generics are NOT allowed.
 ///     // - The `ctx` argument can be omitted for brevity.
-///     // - The function must not return anything (void).
+///     // - The function may return a value, e.g. the ID of a created item or a `Result`. The
+///     //   generated updater function then waits until the host has processed the message and
+///     //   returns the value as `Result<Ret, emyu::Error>`, failing with `Error::ReplyDropped` if
+///     //   the host drops the message instead. Its message variant gets an extra
+///     //   `__reply: Reply<Ret>` field.
 ///     //
 ///     // The visibility of the function determines its visibility on the updater struct.
 ///     //
-///     // Function names for the updater struct will inherit the name of this function.
+///     // Function names for the updater struct will inherit the name of this function. Besides
+///     // the `async fn set_name`, the updater struct also gets `fn try_set_name`, which fails
+///     // instead of waiting when the channel to the host is full, and `fn set_name_blocking`,
+///     // which blocks the current thread. Both return `emyu::Error` instead of panicking. The
+///     // `try_` variant of an updater returning a value hands back a `ReplyReceiver` to await.
+///     // Finally, `async fn set_name_and_wait` only returns once the host has applied the message
+///     // and flushed the signals, so getters read afterwards already see the new name.
 ///     #[emyu(
 ///         // Name config. If not passed, the message name will be the function name converted
 ///         // to PascalCase. For example, `set_name` becomes `SetName`.
 ///         message = "SetName",
 ///
+///         // (only for updaters returning `Result<T, E>`) Routes errors somewhere besides the
+///         // caller. `signal` names a `Signal<Option<E>>` field of the model which is set to the
+///         // last error and cleared once the updater succeeds again, so `E` must implement
+///         // `Clone`. `host` reports errors as `HostEvent::UpdateFailed` on the host's event
+///         // streams, so `E` must implement `Display`.
+///         errors(signal = "last_error", host),
+///
+///         // (only for updaters returning nothing) Keeps at most one message of this variant
+///         // waiting in the host's queue: a newer one replaces the pending one in place instead of
+///         // queuing behind it. Useful for slider drags and scroll positions. Only applies to the
+///         // root model, since the host can't look inside the messages of zoomed children. The
+///         // host takes at most a lane's buffer size of messages out of the channel ahead of
+///         // time, so messages past that still count against its backpressure.
+///         coalesce,
+///
+///         // (only for updaters taking a single `Lifecycle` and returning nothing) Receives the
+///         // lifecycle transitions the host is told about, e.g. to stop polling on
+///         // `Lifecycle::Paused` or drop caches on `Lifecycle::LowMemory`. At most one updater
+///         // may be marked, and only the root model's is called.
+///         on_lifecycle,
+///
 ///         // Attributes config, these can be specified multiple times:
 ///         // `#[some_meta] fn set_name(&mut self, message: SetNameMessage) -> { /* ... */ }`
 ///         meta(
@@ -151,6 +182,12 @@ use syn::Meta;
 ///         // For example, `location` becomes `GetLocationMessage`.
 ///         message = "GetLocationMessage",
 ///
+///         // (only when `persistence` feature is enabled) Loads the field from the key-value
+///         // store in the world under this key when the host is built, and writes it back
+///         // whenever the signal is flushed dirty. The signal's value must implement `Serialize`
+///         // and `Deserialize`.
+///         persist = "location",
+///
 ///         // Attributes config, these can be specified multiple times:
 ///         // `#[some_meta] fn location(&self, message: GetLocationMessage) -> String { /* ... */ }`
 ///         meta(
@@ -159,6 +196,34 @@ use syn::Meta;
 ///         ),
 ///     )]
 ///     pub(super) fn location(&self) -> Signal<String>;
+///
+///     // A subscriptions function. At most one may be declared.
+///     // The function must follow this shape:
+///     // `fn $fn_name(&self) -> Subscriptions<Self> { /* ... */ }`
+///     //
+///     // It becomes the model's `Model::subscriptions` implementation. The host calls it after
+///     // every update, starting subscriptions whose key appeared and stopping the ones whose key
+///     // is gone.
+///     #[emyu(subscriptions)]
+///     fn subscriptions(&self) -> Subscriptions<Self> {
+///         if self.polling {
+///             Subscriptions::none().with("poll", || poll_stream())
+///         } else {
+///             Subscriptions::none()
+///         }
+///     }
+///
+///     // An init function. At most one may be declared.
+///     // The function must follow this shape:
+///     // `fn $fn_name(&mut self[, ctx: &mut UpdateContext<App>]) { /* ... */ }`
+///     //
+///     // It becomes the model's `Model::init` implementation. The host calls it once when it
+///     // starts, before processing any message, so it can emit the commands loading the initial
+///     // data.
+///     #[emyu(init)]
+///     fn init(&mut self, ctx: &mut UpdateContext<App>) {
+///         ctx.emit_command(LoadProfile);
+///     }
 /// }
 /// ```
 #[derive(FromMeta)]
@@ -167,7 +232,7 @@ pub struct ModelArgs {
     pub for_app: Ident,
 
     #[darling(default)]
-    pub message: MessageConfig,
+    pub message: Option<MessageDef>,
 
     #[darling(default)]
     pub dispatcher: Option<DispatcherDef>,
@@ -1,13 +1,14 @@
 use crate::model::attr::raw::ProcessedMeta;
 use crate::model::attr::{ModelArgs, ModelProperties, NewMethodArgs};
 use crate::model::{
-    ModelContext, ParsedFnArg, ParsedGetterFn, ParsedNewFn, ParsedUpdaterFn, ParsedUpdaterGetterFn,
+    ModelContext, ParsedFnArg, ParsedGetterFn, ParsedInitFn, ParsedNewFn, ParsedSubscriptionsFn,
+    ParsedUpdaterFn, ParsedUpdaterGetterFn,
 };
 use crate::utils::ThisCrate;
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 use std::iter;
-use syn::{TypePath, Visibility};
+use syn::{ReturnType, Type, TypePath, Visibility};
 
 impl<'a> ModelContext<'a> {
     pub(super) fn generate(&self) -> TokenStream {
@@ -40,10 +41,31 @@ impl<'a> ModelContext<'a> {
             .getters
             .iter()
             .map(|g| g.generate_accumulate_signals(crate_));
+        let persisted_fields_trait_fns = self.generate_persisted_fields_trait_fns();
+        let describe_message_trait_fn = self.generate_describe_message_trait_fn();
+        let coalesce_key_trait_fn = self.generate_coalesce_key_trait_fn();
+        let lifecycle_message_trait_fn = self.generate_lifecycle_message_trait_fn();
+        let (subscriptions_model_fn, subscriptions_trait_fn) = self
+            .subscriptions
+            .as_ref()
+            .map(|s| (s.generate_model_fn(), s.generate_trait_fn()))
+            .unzip();
+        let (init_model_fn, init_trait_fn) = self
+            .init
+            .as_ref()
+            .map(|i| {
+                (
+                    i.generate_model_fn(crate_, for_app),
+                    i.generate_trait_fn(crate_, for_app),
+                )
+            })
+            .unzip();
 
         quote! {
             impl #model_ty {
                 #(#model_fns)*
+                #subscriptions_model_fn
+                #init_model_fn
             }
             impl #crate_::Model for #model_ty {
                 type ForApp = #for_app;
@@ -55,6 +77,13 @@ impl<'a> ModelContext<'a> {
                     }
                 }
 
+                #subscriptions_trait_fn
+                #init_trait_fn
+                #persisted_fields_trait_fns
+                #describe_message_trait_fn
+                #coalesce_key_trait_fn
+                #lifecycle_message_trait_fn
+
                 fn __accumulate_signals(
                     &self,
                     signals: &mut #crate_::__macros::alloc::collections::VecDeque<#crate_::__macros::Shared<dyn #crate_::__macros::FlushSignals>>,
@@ -67,12 +96,134 @@ impl<'a> ModelContext<'a> {
     }
 }
 
+impl<'a> ModelContext<'a> {
+    fn generate_persisted_fields_trait_fns(&self) -> Option<TokenStream> {
+        let crate_ = &self.crate_;
+        let (keys, field_names): (Vec<_>, Vec<_>) = self
+            .getters
+            .iter()
+            .filter_map(|g| {
+                let args = &g.common.method_args;
+                Some((args.persist.as_ref()?, &args.fn_name))
+            })
+            .unzip();
+        if keys.is_empty() {
+            return None;
+        }
+
+        Some(quote! {
+            fn __load_fields(
+                &mut self,
+                store: &dyn #crate_::KeyValueStore,
+                _: #crate_::__private::Token,
+            ) {
+                #(#crate_::__macros::__load_field(store, #keys, &self.#field_names);)*
+            }
+
+            fn __save_fields(
+                &self,
+                store: &dyn #crate_::KeyValueStore,
+                _: #crate_::__private::Token,
+            ) {
+                #(#crate_::__macros::__save_field(store, #keys, &self.#field_names);)*
+            }
+        })
+    }
+}
+
+impl<'a> ModelContext<'a> {
+    fn generate_describe_message_trait_fn(&self) -> Option<TokenStream> {
+        if self.updaters.is_empty() {
+            return None;
+        }
+
+        let crate_ = &self.crate_;
+        let message_name = &self.args.message.name;
+        let describe_cases = self
+            .updaters
+            .iter()
+            .map(|u| u.generate_describe_case(message_name));
+
+        Some(quote! {
+            fn __describe_message(
+                message: &#message_name,
+                _: #crate_::__private::Token,
+            ) -> &'static str {
+                match message {
+                    #(#describe_cases)*
+                }
+            }
+        })
+    }
+}
+
+impl<'a> ModelContext<'a> {
+    fn generate_coalesce_key_trait_fn(&self) -> Option<TokenStream> {
+        if !self.updaters.iter().any(|u| u.common.method_args.coalesce) {
+            return None;
+        }
+
+        let crate_ = &self.crate_;
+        let message_name = &self.args.message.name;
+        let coalesce_cases = self
+            .updaters
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| u.common.method_args.coalesce)
+            .map(|(index, u)| {
+                let variant_name = &u.common.method_args.message.name;
+                quote! {
+                    #message_name::#variant_name { .. } => ::core::option::Option::Some(#index),
+                }
+            });
+
+        Some(quote! {
+            fn __coalesce_key(
+                message: &#message_name,
+                _: #crate_::__private::Token,
+            ) -> ::core::option::Option<usize> {
+                #[allow(unreachable_patterns)]
+                match message {
+                    #(#coalesce_cases)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        })
+    }
+}
+
+impl<'a> ModelContext<'a> {
+    fn generate_lifecycle_message_trait_fn(&self) -> Option<TokenStream> {
+        let updater = self
+            .updaters
+            .iter()
+            .find(|u| u.common.method_args.on_lifecycle)?;
+
+        let crate_ = &self.crate_;
+        let message_name = &self.args.message.name;
+        let variant_name = &updater.common.method_args.message.name;
+        let field_name = updater.fn_args[0].name;
+
+        Some(quote! {
+            fn __lifecycle_message(
+                lifecycle: #crate_::Lifecycle,
+                _: #crate_::__private::Token,
+            ) -> ::core::option::Option<#message_name> {
+                ::core::option::Option::Some(#message_name::#variant_name { #field_name: lifecycle })
+            }
+        })
+    }
+}
+
 impl<'a> ModelContext<'a> {
     fn generate_message(&self) -> TokenStream {
         let vis = &self.struct_vis;
         let name = &self.args.message.name;
         let outer_meta = &self.args.message.outer_meta;
-        let variants = self.updaters.iter().map(|u| u.generate_message_variant());
+        let variants = self
+            .updaters
+            .iter()
+            .map(|u| u.generate_message_variant(&self.crate_));
 
         quote! {
             #(#[#outer_meta])*
@@ -175,7 +326,7 @@ impl<'a> ModelContext<'a> {
             |a| &a.updater,
             |new_fn, crate_, dispatcher_name| new_fn.generate_for_updater(crate_, dispatcher_name),
             |m| &m.updaters,
-            |u| u.generate_updater_fn(&self.args.message.name),
+            |u| u.generate_updater_fn(crate_, &self.args.message.name),
         )
     }
 }
@@ -289,15 +440,30 @@ impl<'a> ParsedUpdaterGetterFn<'a> {
     }
 }
 
+fn reply_ident() -> Ident {
+    Ident::new("__reply", Span::call_site())
+}
+
 impl<'a> ParsedUpdaterFn<'a> {
-    fn generate_message_variant(&self) -> TokenStream {
+    fn reply_ty(&self) -> Option<&Type> {
+        match self.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some(ty),
+        }
+    }
+
+    fn generate_message_variant(&self, crate_: &ThisCrate) -> TokenStream {
         let variant_name = &self.common.method_args.message.name;
         let outer_meta = &self.common.method_args.message.outer_meta;
         let fields = self.fn_args.iter().map(|fa| fa.generate_field());
+        let reply_field = self.reply_ty().map(|reply_ty| {
+            let reply = reply_ident();
+            quote! { #reply: #crate_::Reply<#reply_ty> }
+        });
 
         quote! {
             #(#[#outer_meta])*
-            #variant_name { #(#fields),* },
+            #variant_name { #(#fields,)* #reply_field },
         }
     }
 
@@ -311,8 +477,64 @@ impl<'a> ParsedUpdaterFn<'a> {
             .cloned()
             .chain(iter::once(Ident::new("ctx", Span::call_site())));
 
+        if self.reply_ty().is_none() {
+            return quote! {
+                #message_name::#variant_name { #(#field_names),* } => self.#fn_name(#(#field_names_and_ctx),*),
+            };
+        }
+
+        let reply = reply_ident();
+        let route_to_signal = self
+            .common
+            .method_args
+            .errors
+            .as_ref()
+            .and_then(|e| e.signal.as_ref())
+            .map(|signal| {
+                quote! {
+                    match &result {
+                        ::core::result::Result::Err(error) => {
+                            self.#signal.writer().set(::core::option::Option::Some(::core::clone::Clone::clone(error)));
+                        }
+                        ::core::result::Result::Ok(_) => {
+                            if self.#signal.reader().read().is_some() {
+                                self.#signal.writer().set(::core::option::Option::None);
+                            }
+                        }
+                    }
+                }
+            });
+        let route_to_host = self
+            .common
+            .method_args
+            .errors
+            .as_ref()
+            .filter(|e| e.host)
+            .map(|_| {
+                let description = format!("{message_name}::{variant_name}");
+                quote! {
+                    if let ::core::result::Result::Err(error) = &result {
+                        ctx.__report_error(#description, error);
+                    }
+                }
+            });
+
         quote! {
-            #message_name::#variant_name { #(#field_names),* } => self.#fn_name(#(#field_names_and_ctx),*),
+            #message_name::#variant_name { #(#field_names,)* #reply } => {
+                let result = self.#fn_name(#(#field_names_and_ctx),*);
+                #route_to_signal
+                #route_to_host
+                #reply.send(result);
+            }
+        }
+    }
+
+    fn generate_describe_case(&self, message_name: &Ident) -> TokenStream {
+        let variant_name = &self.common.method_args.message.name;
+        let description = format!("{message_name}::{variant_name}");
+
+        quote! {
+            #message_name::#variant_name { .. } => #description,
         }
     }
 
@@ -325,13 +547,14 @@ impl<'a> ParsedUpdaterFn<'a> {
                 .unwrap_or_else(|| Ident::new("_", Span::call_site()));
             iter::once(quote! { #ctx: &mut #crate_::UpdateContext<#for_app> })
         });
+        let output = self.output;
         let block = self.block;
         quote! {
-            fn #fn_name(&mut self, #(#fn_args),*) { #block }
+            fn #fn_name(&mut self, #(#fn_args),*) #output #block
         }
     }
 
-    fn generate_updater_fn(&self, message_name: &Ident) -> TokenStream {
+    fn generate_updater_fn(&self, crate_: &ThisCrate, message_name: &Ident) -> TokenStream {
         self.common
             .generate_updater_getter_fn(|vis, meta, fn_name, variant_name| {
                 let field_names = self.fn_args.iter().map(|fa| fa.name).collect::<Vec<_>>();
@@ -341,10 +564,77 @@ impl<'a> ParsedUpdaterFn<'a> {
                     .map(|fa| fa.generate_fn_arg())
                     .collect::<Vec<_>>();
 
+                let try_fn_name = format_ident!("try_{fn_name}");
+                let blocking_fn_name = format_ident!("{fn_name}_blocking");
+                let and_wait_fn_name = format_ident!("{fn_name}_and_wait");
+
+                let Some(reply_ty) = self.reply_ty() else {
+                    return quote! {
+                        #(#[#meta])*
+                        #vis async fn #fn_name(&mut self, #(#fn_args),*) {
+                            self.0.send(#message_name::#variant_name { #(#field_names),* }).await
+                        }
+
+                        #(#[#meta])*
+                        #vis fn #try_fn_name(&mut self, #(#fn_args),*) -> ::core::result::Result<(), #crate_::Error> {
+                            self.0.try_send_now(#message_name::#variant_name { #(#field_names),* })
+                        }
+
+                        #(#[#meta])*
+                        #vis fn #blocking_fn_name(&mut self, #(#fn_args),*) -> ::core::result::Result<(), #crate_::Error> {
+                            self.0.send_blocking(#message_name::#variant_name { #(#field_names),* })
+                        }
+
+                        #(#[#meta])*
+                        #vis async fn #and_wait_fn_name(&mut self, #(#fn_args),*) -> ::core::result::Result<(), #crate_::Error> {
+                            self.0.send_and_wait(#message_name::#variant_name { #(#field_names),* }).await
+                        }
+                    };
+                };
+
+                // updaters returning a value wait until the host has processed the message, except
+                // for the `try_` variant which hands back the receiving end of the reply instead
+                let reply = reply_ident();
                 quote! {
                     #(#[#meta])*
-                    #vis async fn #fn_name(&mut self, #(#fn_args),*) {
-                        self.0.send(#message_name::#variant_name { #(#field_names),* }).await
+                    #vis async fn #fn_name(
+                        &mut self,
+                        #(#fn_args),*
+                    ) -> ::core::result::Result<#reply_ty, #crate_::Error> {
+                        let (#reply, result) = #crate_::Reply::channel();
+                        self.0.try_send(#message_name::#variant_name { #(#field_names,)* #reply }).await?;
+                        result.await.map_err(|_| #crate_::Error::ReplyDropped)
+                    }
+
+                    #(#[#meta])*
+                    #vis fn #try_fn_name(
+                        &mut self,
+                        #(#fn_args),*
+                    ) -> ::core::result::Result<#crate_::ReplyReceiver<#reply_ty>, #crate_::Error> {
+                        let (#reply, result) = #crate_::Reply::channel();
+                        self.0.try_send_now(#message_name::#variant_name { #(#field_names,)* #reply })?;
+                        ::core::result::Result::Ok(result)
+                    }
+
+                    #(#[#meta])*
+                    #vis fn #blocking_fn_name(
+                        &mut self,
+                        #(#fn_args),*
+                    ) -> ::core::result::Result<#reply_ty, #crate_::Error> {
+                        let (#reply, result) = #crate_::Reply::channel();
+                        self.0.send_blocking(#message_name::#variant_name { #(#field_names,)* #reply })?;
+                        #crate_::__macros::futures::executor::block_on(result)
+                            .map_err(|_| #crate_::Error::ReplyDropped)
+                    }
+
+                    #(#[#meta])*
+                    #vis async fn #and_wait_fn_name(
+                        &mut self,
+                        #(#fn_args),*
+                    ) -> ::core::result::Result<#reply_ty, #crate_::Error> {
+                        let (#reply, result) = #crate_::Reply::channel();
+                        self.0.send_and_wait(#message_name::#variant_name { #(#field_names,)* #reply }).await?;
+                        result.await.map_err(|_| #crate_::Error::ReplyDropped)
                     }
                 }
             })
@@ -409,3 +699,52 @@ impl<'a> ParsedGetterFn<'a> {
             })
     }
 }
+
+impl<'a> ParsedSubscriptionsFn<'a> {
+    fn generate_model_fn(&self) -> TokenStream {
+        let Self {
+            fn_name,
+            ret_ty,
+            block,
+        } = *self;
+        quote! {
+            fn #fn_name(&self) #ret_ty #block
+        }
+    }
+
+    fn generate_trait_fn(&self) -> TokenStream {
+        let Self {
+            fn_name, ret_ty, ..
+        } = *self;
+        quote! {
+            fn subscriptions(&self) #ret_ty {
+                Self::#fn_name(self)
+            }
+        }
+    }
+}
+
+impl<'a> ParsedInitFn<'a> {
+    fn generate_model_fn(&self, crate_: &ThisCrate, for_app: &Ident) -> TokenStream {
+        let Self {
+            fn_name,
+            ctx,
+            block,
+        } = *self;
+        let ctx = ctx
+            .cloned()
+            .unwrap_or_else(|| Ident::new("_", Span::call_site()));
+        quote! {
+            fn #fn_name(&mut self, #ctx: &mut #crate_::UpdateContext<#for_app>) #block
+        }
+    }
+
+    fn generate_trait_fn(&self, crate_: &ThisCrate, for_app: &Ident) -> TokenStream {
+        let fn_name = self.fn_name;
+        quote! {
+            fn init(&mut self, ctx: &mut #crate_::UpdateContext<#for_app>) {
+                Self::#fn_name(self, ctx)
+            }
+        }
+    }
+}
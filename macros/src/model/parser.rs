@@ -1,8 +1,8 @@
 use crate::model::attr::raw::ProcessedMeta;
 use crate::model::attr::{ModelArgs, NewMethodArgs, UpdaterGetterMethodArgs, raw};
 use crate::model::{
-    FnKind, ModelContext, ParsedFnArg, ParsedGetterFn, ParsedNewFn, ParsedUpdaterFn,
-    ParsedUpdaterGetterFn, RawModelArgs,
+    FnKind, ModelContext, ParsedFnArg, ParsedGetterFn, ParsedInitFn, ParsedNewFn,
+    ParsedSubscriptionsFn, ParsedUpdaterFn, ParsedUpdaterGetterFn, RawModelArgs,
 };
 use crate::utils;
 use crate::utils::{InterfaceImpl, MaybeStubFn, ThisCrate};
@@ -94,7 +94,9 @@ impl<'a> ModelContext<'a> {
             new_fn,
             updaters,
             getters,
-        } = ParsedFnsSecondPass::parse(items, &crate_, attrs.flutter_rust_bridge());
+            subscriptions,
+            init,
+        } = ParsedFnsSecondPass::parse(items, &crate_, attrs.flutter_rust_bridge())?;
         Ok(Self {
             args: ModelArgs::parse(attrs, model_name, &crate_, ty_path.span())?,
             crate_,
@@ -103,11 +105,14 @@ impl<'a> ModelContext<'a> {
             new_fn,
             updaters,
             getters,
+            subscriptions,
+            init,
         })
     }
 }
 
 struct ParsedFnFirstPass<'a> {
+    name: &'a Ident,
     vis: &'a Visibility,
     fn_args: Vec<ParsedFnArg<'a>>,
     kind: FnKind<'a>,
@@ -122,6 +127,7 @@ impl<'a> ParsedFnFirstPass<'a> {
         let args = raw::MethodArgs::from_attributes(&item.attrs)?;
         let mut kind = FnKind::analyze(item, args, crate_, flutter_rust_bridge)?;
         Ok(Self {
+            name: &item.sig.ident,
             vis: &item.vis,
             fn_args: item
                 .sig
@@ -216,6 +222,28 @@ impl<'a> FnKind<'a> {
             }
         }
 
+        // `E` of updaters returning `Result<T, E>`
+        fn extract_result_err_ty(ret_ty: &ReturnType) -> Option<&Type> {
+            if let ReturnType::Type(_, ty) = ret_ty
+                && let Type::Path(TypePath {
+                    path: Path { segments, .. },
+                    ..
+                }) = &**ty
+                && let Some(PathSegment {
+                    ident,
+                    arguments:
+                        PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+                }) = segments.last()
+                && ident == "Result"
+                && args.len() == 2
+                && let GenericArgument::Type(err_ty) = &args[1]
+            {
+                Some(err_ty)
+            } else {
+                None
+            }
+        }
+
         fn extract_inner_signal_ty(ret_ty: &ReturnType) -> Option<&Type> {
             if let ReturnType::Type(_, ty) = ret_ty
                 && let Type::Path(TypePath {
@@ -248,6 +276,59 @@ impl<'a> FnKind<'a> {
             item.block.as_ref(),
         );
 
+        if args.subscriptions {
+            return match (self_ty, &item.sig.output, has_no_fn_args, block) {
+                (Some(SelfTy::Shared), ret_ty @ ReturnType::Type(..), true, Some(block)) => {
+                    if args.name.is_some()
+                        || args.message.is_some()
+                        || args.meta.is_some()
+                        || args.persist.is_some()
+                        || args.errors.is_some()
+                        || args.coalesce
+                        || args.on_lifecycle
+                        || args.init
+                    {
+                        return Err(syn::Error::new_spanned(
+                            &item.sig,
+                            "`#[emyu(subscriptions)]` does not accept any other options",
+                        ));
+                    }
+                    Ok(Self::Subscriptions { ret_ty, block })
+                }
+                _ => Err(syn::Error::new_spanned(
+                    &item.sig,
+                    "`#[emyu(subscriptions)]` functions must have the shape \
+                     `fn(&self) -> Subscriptions<Self> { ... }`",
+                )),
+            };
+        }
+
+        if args.init {
+            return match (self_ty, &item.sig.output, block) {
+                (Some(SelfTy::Mutable), ReturnType::Default, Some(block)) => {
+                    if args.name.is_some()
+                        || args.message.is_some()
+                        || args.meta.is_some()
+                        || args.persist.is_some()
+                        || args.errors.is_some()
+                        || args.coalesce
+                        || args.on_lifecycle
+                    {
+                        return Err(syn::Error::new_spanned(
+                            &item.sig,
+                            "`#[emyu(init)]` does not accept any other options",
+                        ));
+                    }
+                    Ok(Self::Init { ctx: None, block })
+                }
+                _ => Err(syn::Error::new_spanned(
+                    &item.sig,
+                    "`#[emyu(init)]` functions must have the shape \
+                     `fn(&mut self[, ctx: &mut UpdateContext<App>]) { ... }`",
+                )),
+            };
+        }
+
         match (fn_name.as_str(), self_ty, ret_ty, has_no_fn_args, block) {
             ("new", None, None, true, None) => Ok(Self::New(NewMethodArgs::parse(
                 args,
@@ -255,7 +336,27 @@ impl<'a> FnKind<'a> {
                 crate_,
                 flutter_rust_bridge,
             )?)),
-            (_, Some(SelfTy::Mutable), None, _, Some(block)) => {
+            (_, Some(SelfTy::Mutable), _, _, Some(block)) => {
+                if args.errors.is_some() && extract_result_err_ty(&item.sig.output).is_none() {
+                    return Err(syn::Error::new_spanned(
+                        &item.sig,
+                        "`#[emyu(errors(...))]` requires the updater to return a `Result`",
+                    ));
+                }
+                // a replaced message would never reply to its sender
+                if args.coalesce && matches!(item.sig.output, ReturnType::Type(..)) {
+                    return Err(syn::Error::new_spanned(
+                        &item.sig,
+                        "`#[emyu(coalesce)]` can't be used on updaters returning a value",
+                    ));
+                }
+                // the host sends lifecycle messages itself, without waiting for a reply
+                if args.on_lifecycle && matches!(item.sig.output, ReturnType::Type(..)) {
+                    return Err(syn::Error::new_spanned(
+                        &item.sig,
+                        "`#[emyu(on_lifecycle)]` can't be used on updaters returning a value",
+                    ));
+                }
                 Ok(Self::Updater {
                     args: UpdaterGetterMethodArgs::parse_updater(
                         args,
@@ -265,6 +366,7 @@ impl<'a> FnKind<'a> {
                         flutter_rust_bridge,
                     )?,
                     ctx: None, // ctx ident will be found in FnArgs parsing
+                    output: &item.sig.output,
                     block,
                 })
             }
@@ -288,7 +390,7 @@ impl<'a> FnKind<'a> {
 
 impl<'a> FnKind<'a> {
     fn fill_update_context(&mut self, ctx: &'a Ident) {
-        if let FnKind::Updater { ctx: ctx_field, .. } = self {
+        if let FnKind::Updater { ctx: ctx_field, .. } | FnKind::Init { ctx: ctx_field, .. } = self {
             *ctx_field = Some(ctx);
         }
     }
@@ -346,6 +448,8 @@ struct ParsedFnsSecondPass<'a> {
     new_fn: ParsedNewFn,
     updaters: Vec<ParsedUpdaterFn<'a>>,
     getters: Vec<ParsedGetterFn<'a>>,
+    subscriptions: Option<ParsedSubscriptionsFn<'a>>,
+    init: Option<ParsedInitFn<'a>>,
 }
 
 impl<'a> ParsedFnsSecondPass<'a> {
@@ -353,10 +457,13 @@ impl<'a> ParsedFnsSecondPass<'a> {
         items: Vec<ParsedFnFirstPass<'a>>,
         crate_: &ThisCrate,
         flutter_rust_bridge: bool,
-    ) -> Self {
+    ) -> syn::Result<Self> {
         let mut new_fn = ParsedNewFn::default();
         let mut updaters = Vec::with_capacity(items.len());
         let mut getters = Vec::with_capacity(items.len());
+        let mut subscriptions = None;
+        let mut has_on_lifecycle = false;
+        let mut init = None;
 
         for item in items {
             match item.kind {
@@ -369,16 +476,36 @@ impl<'a> ParsedFnsSecondPass<'a> {
                 FnKind::Updater {
                     args: method_args,
                     ctx,
+                    output,
                     block,
-                } => updaters.push(ParsedUpdaterFn {
-                    common: ParsedUpdaterGetterFn {
-                        vis: item.vis,
-                        method_args,
-                    },
-                    fn_args: item.fn_args,
-                    ctx,
-                    block,
-                }),
+                } => {
+                    if method_args.on_lifecycle {
+                        if has_on_lifecycle {
+                            return Err(syn::Error::new_spanned(
+                                item.name,
+                                "only one `#[emyu(on_lifecycle)]` updater is allowed",
+                            ));
+                        }
+                        if item.fn_args.len() != 1 {
+                            return Err(syn::Error::new_spanned(
+                                item.name,
+                                "`#[emyu(on_lifecycle)]` updaters must take exactly one \
+                                 `Lifecycle` argument besides the context",
+                            ));
+                        }
+                        has_on_lifecycle = true;
+                    }
+                    updaters.push(ParsedUpdaterFn {
+                        common: ParsedUpdaterGetterFn {
+                            vis: item.vis,
+                            method_args,
+                        },
+                        fn_args: item.fn_args,
+                        ctx,
+                        output,
+                        block,
+                    })
+                }
                 FnKind::Getter {
                     args: method_args,
                     ty,
@@ -389,6 +516,38 @@ impl<'a> ParsedFnsSecondPass<'a> {
                     },
                     ret_ty: ty,
                 }),
+                FnKind::Subscriptions { ret_ty, block } => {
+                    if subscriptions.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            item.name,
+                            "only one `#[emyu(subscriptions)]` function is allowed",
+                        ));
+                    }
+                    subscriptions = Some(ParsedSubscriptionsFn {
+                        fn_name: item.name,
+                        ret_ty,
+                        block,
+                    });
+                }
+                FnKind::Init { ctx, block } => {
+                    if init.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            item.name,
+                            "only one `#[emyu(init)]` function is allowed",
+                        ));
+                    }
+                    if !item.fn_args.is_empty() {
+                        return Err(syn::Error::new_spanned(
+                            item.name,
+                            "`#[emyu(init)]` functions can't take arguments besides the context",
+                        ));
+                    }
+                    init = Some(ParsedInitFn {
+                        fn_name: item.name,
+                        ctx,
+                        block,
+                    });
+                }
             }
         }
 
@@ -399,10 +558,12 @@ impl<'a> ParsedFnsSecondPass<'a> {
         updaters.shrink_to_fit();
         getters.shrink_to_fit();
 
-        Self {
+        Ok(Self {
             new_fn,
             updaters,
             getters,
-        }
+            subscriptions,
+            init,
+        })
     }
 }